@@ -0,0 +1,55 @@
+extern crate splay_tree_rs;
+
+mod common;
+
+use common::keys;
+use common::report;
+use common::time;
+use splay_tree_rs::ArenaSplayTree;
+use splay_tree_rs::Node;
+use splay_tree_rs::SplayTree;
+use splay_tree_rs::Splayable;
+
+const COUNT: u64 = 100_000;
+
+fn main() {
+    let keys = keys(COUNT);
+    let mut rc_tree = SplayTree::new();
+    let mut arena_tree = ArenaSplayTree::new();
+
+    let rc = time(|| {
+        for &key in &keys {
+            rc_tree.insert(&mut Node::new(key));
+        }
+    });
+    let arena = time(|| {
+        for &key in &keys {
+            arena_tree.insert(key, ());
+        }
+    });
+    report("insert", ("rc", rc), ("arena", arena));
+
+    let rc = time(|| {
+        for &key in keys.iter().rev() {
+            rc_tree.splay(key);
+        }
+    });
+    let arena = time(|| {
+        for &key in keys.iter().rev() {
+            arena_tree.splay(key);
+        }
+    });
+    report("splay", ("rc", rc), ("arena", arena));
+
+    let rc = time(|| {
+        for &key in &keys {
+            rc_tree.delete(key);
+        }
+    });
+    let arena = time(|| {
+        for key in &keys {
+            arena_tree.remove(key);
+        }
+    });
+    report("delete", ("rc", rc), ("arena", arena));
+}
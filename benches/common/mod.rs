@@ -0,0 +1,49 @@
+// every bench is its own crate and uses only some of these
+#![allow(dead_code)]
+
+use std::collections::HashSet;
+use std::time::Duration;
+use std::time::Instant;
+
+// step of the linear congruential generator the benches draw keys from
+pub fn next_seed(seed: &mut u64) -> u64 {
+    *seed = seed
+        .wrapping_mul(6364136223846793005)
+        .wrapping_add(1442695040888963407);
+    *seed
+}
+
+// count distinct keys in random order. repeats are skipped, the Rc tree keeps
+// a duplicate as one more node while the arena tree replaces the value, so
+// they would not do the same work
+pub fn keys(count: u64) -> Vec<u64> {
+    let mut seed: u64 = 3438;
+    let mut seen = HashSet::new();
+    let mut keys = Vec::new();
+    while keys.len() < count as usize {
+        let key = (next_seed(&mut seed) >> 33) % (count * 4);
+        if seen.insert(key) {
+            keys.push(key);
+        }
+    }
+    keys
+}
+
+pub fn time<F: FnOnce()>(f: F) -> Duration {
+    let start = Instant::now();
+    f();
+    start.elapsed()
+}
+
+// one line per operation, the ratio is how many times faster the second is
+pub fn report(name: &str, first: (&str, Duration), second: (&str, Duration)) {
+    println!(
+        "{:<10} {} {:>10.2?}   {} {:>10.2?}   {:.2}x",
+        name,
+        first.0,
+        first.1,
+        second.0,
+        second.1,
+        first.1.as_secs_f64() / second.1.as_secs_f64()
+    );
+}
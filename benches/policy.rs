@@ -0,0 +1,96 @@
+extern crate splay_tree_rs;
+
+mod common;
+
+use common::next_seed;
+use common::report;
+use common::time;
+use splay_tree_rs::ArenaSplayTree;
+use splay_tree_rs::ConcurrentSplayTree;
+use splay_tree_rs::SplayPolicy;
+use splay_tree_rs::Splayable;
+
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+const COUNT: u64 = 100_000;
+const ACCESSES: usize = 1_000_000;
+const THREADS: usize = 4;
+
+const POLICIES: [(&str, SplayPolicy); 4] = [
+    ("always", SplayPolicy::Always),
+    ("1 in 8", SplayPolicy::Sampled(8)),
+    ("1 in 64", SplayPolicy::Sampled(64)),
+    ("depth>32", SplayPolicy::DeeperThan(32)),
+];
+
+// most accesses go to a small hot set, the rest are spread over all keys
+fn accesses(seed: u64) -> Vec<u64> {
+    let mut seed = seed;
+    (0..ACCESSES)
+        .map(|_| {
+            let value = next_seed(&mut seed) >> 33;
+            if value % 10 < 9 {
+                value % 64 * (COUNT / 64)
+            } else {
+                value % COUNT
+            }
+        })
+        .collect()
+}
+
+fn filled(policy: SplayPolicy) -> ArenaSplayTree<u64> {
+    let mut tree = ArenaSplayTree::with_policy(policy);
+    for key in 0..COUNT {
+        tree.insert(key, ());
+    }
+    tree
+}
+
+fn main() {
+    let keys = accesses(3438);
+
+    println!("single thread, {} accesses", ACCESSES);
+    let mut always = Duration::default();
+    for &(name, policy) in POLICIES.iter() {
+        let mut tree = filled(policy);
+        let elapsed = time(|| {
+            for &key in &keys {
+                tree.splay(key);
+            }
+        });
+        if policy == SplayPolicy::Always {
+            always = elapsed;
+        }
+        report(name, ("always", always), ("policy", elapsed));
+    }
+
+    println!("{} threads, {} accesses each", THREADS, ACCESSES);
+    for &(name, policy) in POLICIES.iter() {
+        let tree = Arc::new(ConcurrentSplayTree::with_policy(policy));
+        for key in 0..COUNT {
+            tree.insert(key, ());
+        }
+        let elapsed = time(|| {
+            let readers: Vec<_> = (0..THREADS)
+                .map(|id| {
+                    let tree = Arc::clone(&tree);
+                    let keys = accesses(3438 + id as u64);
+                    thread::spawn(move || {
+                        for key in keys {
+                            tree.splay(&key);
+                        }
+                    })
+                })
+                .collect();
+            for reader in readers {
+                reader.join().unwrap();
+            }
+        });
+        if policy == SplayPolicy::Always {
+            always = elapsed;
+        }
+        report(name, ("always", always), ("policy", elapsed));
+    }
+}
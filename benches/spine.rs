@@ -0,0 +1,38 @@
+extern crate splay_tree_rs;
+
+mod common;
+
+use common::time;
+use splay_tree_rs::Node;
+use splay_tree_rs::SplayTree;
+use splay_tree_rs::Splayable;
+
+use std::time::Duration;
+
+const REPEAT: u32 = 5;
+
+// ascending insertions leave one left path of length n, splaying its bottom
+// walks every node once. with constant work per node the time per node
+// stays flat as n grows
+fn main() {
+    let mut length = 1 << 10;
+    while length <= 1 << 17 {
+        let mut total = Duration::default();
+        for _ in 0..REPEAT {
+            let mut tree = SplayTree::new();
+            for key in 0..length {
+                tree.insert(&mut Node::new(key));
+            }
+            total += time(|| tree.splay(0));
+            assert_eq!(tree.len(), length as usize);
+        }
+        let splay = total / REPEAT;
+        println!(
+            "path {:>7}   splay {:>10.2?}   per node {:>8.2?}",
+            length,
+            splay,
+            splay / length
+        );
+        length *= 2;
+    }
+}
@@ -0,0 +1,116 @@
+extern crate splay_tree_rs;
+
+mod common;
+
+use common::keys;
+use common::report;
+use common::time;
+use splay_tree_rs::ArenaSplayTree;
+use splay_tree_rs::Node;
+use splay_tree_rs::SplayStrategy;
+use splay_tree_rs::SplayTree;
+use splay_tree_rs::Splayable;
+
+const COUNT: u64 = 100_000;
+
+fn main() {
+    let keys = keys(COUNT);
+    let mut top_down = ArenaSplayTree::with_strategy(SplayStrategy::TopDown);
+    let mut bottom_up = ArenaSplayTree::with_strategy(SplayStrategy::BottomUp);
+
+    let first = time(|| {
+        for &key in &keys {
+            top_down.insert(key, ());
+        }
+    });
+    let second = time(|| {
+        for &key in &keys {
+            bottom_up.insert(key, ());
+        }
+    });
+    report("insert", ("top-down", first), ("bottom-up", second));
+
+    let first = time(|| {
+        for &key in keys.iter().rev() {
+            top_down.splay(key);
+        }
+    });
+    let second = time(|| {
+        for &key in keys.iter().rev() {
+            bottom_up.splay(key);
+        }
+    });
+    report("splay", ("top-down", first), ("bottom-up", second));
+
+    // with handles taken up front the bottom-up splay skips the search, the
+    // top-down one still has to start from the root
+    let handles: Vec<_> = keys
+        .iter()
+        .map(|key| bottom_up.handle(key).unwrap())
+        .collect();
+    let first = time(|| {
+        for &key in &keys {
+            top_down.splay(key);
+        }
+    });
+    let second = time(|| {
+        for &handle in &handles {
+            bottom_up.splay_handle(handle);
+        }
+    });
+    report("handle", ("top-down", first), ("bottom-up", second));
+
+    let first = time(|| {
+        for key in &keys {
+            top_down.remove(key);
+        }
+    });
+    let second = time(|| {
+        for key in &keys {
+            bottom_up.remove(key);
+        }
+    });
+    report("delete", ("top-down", first), ("bottom-up", second));
+
+    // the Rc tree has no parent links, its bottom-up splay walks down once to
+    // record the path and then rotates back up along it
+    let mut top_down = SplayTree::new();
+    let mut bottom_up = SplayTree::new();
+    bottom_up.set_strategy(SplayStrategy::BottomUp);
+
+    let first = time(|| {
+        for &key in &keys {
+            top_down.insert(&mut Node::new(key));
+        }
+    });
+    let second = time(|| {
+        for &key in &keys {
+            bottom_up.insert(&mut Node::new(key));
+        }
+    });
+    report("rc insert", ("top-down", first), ("bottom-up", second));
+
+    let first = time(|| {
+        for &key in keys.iter().rev() {
+            top_down.splay(key);
+        }
+    });
+    let second = time(|| {
+        for &key in keys.iter().rev() {
+            bottom_up.splay(key);
+        }
+    });
+    report("rc splay", ("top-down", first), ("bottom-up", second));
+
+    let first = time(|| {
+        for &key in &keys {
+            top_down.delete(key);
+        }
+    });
+    let second = time(|| {
+        for &key in &keys {
+            bottom_up.delete(key);
+        }
+    });
+    report("rc delete", ("top-down", first), ("bottom-up", second));
+}
@@ -0,0 +1,589 @@
+use crate::SplayPolicy;
+use crate::Splayable;
+
+use std::cmp::Ordering;
+use std::mem;
+
+// index of the missing child, or of the parent of the root
+const NIL: u32 = u32::MAX;
+
+#[derive(Clone, Debug)]
+struct ArenaNode<K, V> {
+    key: K,
+    value: V,
+    left: u32,
+    right: u32,
+    parent: u32,
+}
+
+// how a key is brought to the root. SplayTree takes either as well, it has no
+// parent links so its bottom-up splay keeps the search path on a stack, and
+// its splays by index stay top-down. handles, which splay a node without a
+// search, need the parent links and exist on ArenaSplayTree only
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SplayStrategy {
+    // restructure on the way down from the root
+    #[default]
+    TopDown,
+    // find the node first, then rotate it up along its parent links
+    BottomUp,
+}
+
+// names the node of one key, so it can be splayed without a search. it stays
+// valid until that key is removed. a later insertion may reuse the slot, the
+// generation tells the new node apart from the removed one
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Handle(u32, u32);
+
+// splay tree whose nodes live in one vector and link to each other by index,
+// so there is no reference counting and no runtime borrow checking. slots of
+// removed nodes are kept on a free list and reused by later insertions
+#[derive(Clone, Debug)]
+pub struct ArenaSplayTree<K, V = ()> {
+    nodes: Vec<Option<ArenaNode<K, V>>>,
+    free: Vec<u32>,
+    // bumped whenever a slot is freed, outlives clear so old handles stay
+    // stale
+    generations: Vec<u32>,
+    root: u32,
+    len: usize,
+    // lookups and Splayable::splay only restructure when the policy says so
+    policy: SplayPolicy,
+    accesses: usize,
+    strategy: SplayStrategy,
+}
+
+impl<K, V> Default for ArenaSplayTree<K, V> {
+    fn default() -> Self {
+        ArenaSplayTree::with_capacity(0)
+    }
+}
+
+impl<K, V> ArenaSplayTree<K, V> {
+    pub fn with_capacity(capacity: usize) -> Self {
+        ArenaSplayTree {
+            nodes: Vec::with_capacity(capacity),
+            free: Vec::new(),
+            generations: Vec::new(),
+            root: NIL,
+            len: 0,
+            policy: SplayPolicy::Always,
+            accesses: 0,
+            strategy: SplayStrategy::TopDown,
+        }
+    }
+
+    pub fn with_strategy(strategy: SplayStrategy) -> Self {
+        ArenaSplayTree {
+            strategy,
+            ..ArenaSplayTree::default()
+        }
+    }
+
+    pub fn strategy(&self) -> SplayStrategy {
+        self.strategy
+    }
+
+    pub fn set_strategy(&mut self, strategy: SplayStrategy) {
+        self.strategy = strategy;
+    }
+
+    pub fn with_policy(policy: SplayPolicy) -> Self {
+        ArenaSplayTree {
+            policy,
+            ..ArenaSplayTree::default()
+        }
+    }
+
+    pub fn policy(&self) -> SplayPolicy {
+        self.policy
+    }
+
+    pub fn set_policy(&mut self, policy: SplayPolicy) {
+        self.policy = policy;
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    // number of nodes the arena holds without reallocating
+    pub fn capacity(&self) -> usize {
+        self.nodes.capacity()
+    }
+
+    pub fn clear(&mut self) {
+        self.nodes.clear();
+        self.free.clear();
+        for generation in &mut self.generations {
+            *generation = generation.wrapping_add(1);
+        }
+        self.root = NIL;
+        self.len = 0;
+    }
+
+    // walks the child indices with a stack of indices, nothing is splayed so
+    // the handles and the root stay as they are
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        let mut iter = Iter {
+            tree: self,
+            stack: Vec::new(),
+        };
+        iter.push_left_spine(self.root);
+        iter
+    }
+
+    // key and value of the node behind handle, None once it is removed
+    pub fn get_handle(&self, handle: Handle) -> Option<(&K, &V)> {
+        if self.generations.get(handle.0 as usize) != Some(&handle.1) {
+            return None;
+        }
+        let node = self.nodes.get(handle.0 as usize)?.as_ref()?;
+        Some((&node.key, &node.value))
+    }
+
+    // bring the node behind handle to the root without searching for its key,
+    // return false if it is removed
+    pub fn splay_handle(&mut self, handle: Handle) -> bool {
+        if self.get_handle(handle).is_none() {
+            return false;
+        }
+        self.splay_node(handle.0);
+        true
+    }
+
+    // bottom-up splay, every round lifts x by two levels, or by one when its
+    // parent is the root
+    fn splay_node(&mut self, x: u32) {
+        loop {
+            let parent = self.node(x).parent;
+            if parent == NIL {
+                break;
+            }
+            let grandparent = self.node(parent).parent;
+            if grandparent == NIL {
+                // zig
+                self.rotate_up(x);
+                break;
+            }
+            let x_is_left = self.node(parent).left == x;
+            let parent_is_left = self.node(grandparent).left == parent;
+            if x_is_left == parent_is_left {
+                // zig zig, rotate the parent first
+                self.rotate_up(parent);
+                self.rotate_up(x);
+            } else {
+                // zig zag
+                self.rotate_up(x);
+                self.rotate_up(x);
+            }
+        }
+        self.root = x;
+    }
+
+    // rotate x above its parent, x takes the place of the parent under the
+    // grandparent
+    fn rotate_up(&mut self, x: u32) {
+        let parent = self.node(x).parent;
+        let grandparent = self.node(parent).parent;
+        if self.node(parent).left == x {
+            let inner = self.node(x).right;
+            self.set_left(parent, inner);
+            self.set_right(x, parent);
+        } else {
+            let inner = self.node(x).left;
+            self.set_right(parent, inner);
+            self.set_left(x, parent);
+        }
+        self.node_mut(x).parent = grandparent;
+        if grandparent != NIL {
+            let node = self.node_mut(grandparent);
+            if node.left == parent {
+                node.left = x;
+            } else {
+                node.right = x;
+            }
+        }
+    }
+
+    // link child below parent, child may be NIL
+    fn set_left(&mut self, parent: u32, child: u32) {
+        self.node_mut(parent).left = child;
+        if child != NIL {
+            self.node_mut(child).parent = parent;
+        }
+    }
+
+    fn set_right(&mut self, parent: u32, child: u32) {
+        self.node_mut(parent).right = child;
+        if child != NIL {
+            self.node_mut(child).parent = parent;
+        }
+    }
+
+    fn set_root(&mut self, index: u32) {
+        self.root = index;
+        if index != NIL {
+            self.node_mut(index).parent = NIL;
+        }
+    }
+
+    pub(crate) fn value(&self, index: u32) -> Option<&V> {
+        if index == NIL {
+            None
+        } else {
+            Some(&self.node(index).value)
+        }
+    }
+
+    pub(crate) fn value_mut(&mut self, index: u32) -> Option<&mut V> {
+        if index == NIL {
+            None
+        } else {
+            Some(&mut self.node_mut(index).value)
+        }
+    }
+
+    fn node(&self, index: u32) -> &ArenaNode<K, V> {
+        self.nodes[index as usize].as_ref().unwrap()
+    }
+
+    fn node_mut(&mut self, index: u32) -> &mut ArenaNode<K, V> {
+        self.nodes[index as usize].as_mut().unwrap()
+    }
+
+    fn allocate(&mut self, node: ArenaNode<K, V>) -> u32 {
+        self.len += 1;
+        if let Some(index) = self.free.pop() {
+            self.nodes[index as usize] = Some(node);
+            return index;
+        }
+        assert!(
+            self.nodes.len() < NIL as usize,
+            "arena splay tree is out of indices"
+        );
+        self.nodes.push(Some(node));
+        if self.generations.len() < self.nodes.len() {
+            self.generations.push(0);
+        }
+        (self.nodes.len() - 1) as u32
+    }
+
+    fn release(&mut self, index: u32) -> ArenaNode<K, V> {
+        self.len -= 1;
+        self.free.push(index);
+        let generation = &mut self.generations[index as usize];
+        *generation = generation.wrapping_add(1);
+        self.nodes[index as usize].take().unwrap()
+    }
+}
+
+impl<K: Ord, V> ArenaSplayTree<K, V> {
+    pub fn new() -> Self {
+        ArenaSplayTree::default()
+    }
+
+    // insert key with value, an existing key keeps its node and gets the new
+    // value, the old one is returned
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        self.splay_key(&key);
+        if self.root == NIL {
+            self.root = self.allocate(ArenaNode {
+                key,
+                value,
+                left: NIL,
+                right: NIL,
+                parent: NIL,
+            });
+            return None;
+        }
+
+        // NOTE: the root is now key or its neighbour. the new slot takes one
+        // side of it, and set_left and set_right fix the parent indices of
+        // both halves
+        let root = self.root;
+        let (left, right) = match key.cmp(&self.node(root).key) {
+            Ordering::Equal => {
+                return Some(mem::replace(&mut self.node_mut(root).value, value));
+            }
+            Ordering::Less => {
+                let left = mem::replace(&mut self.node_mut(root).left, NIL);
+                (left, root)
+            }
+            Ordering::Greater => {
+                let right = mem::replace(&mut self.node_mut(root).right, NIL);
+                (root, right)
+            }
+        };
+        let index = self.allocate(ArenaNode {
+            key,
+            value,
+            left: NIL,
+            right: NIL,
+            parent: NIL,
+        });
+        self.set_left(index, left);
+        self.set_right(index, right);
+        self.root = index;
+        None
+    }
+
+    pub fn get(&mut self, key: &K) -> Option<&V> {
+        let index = self.access(key);
+        self.value(index)
+    }
+
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        let index = self.access(key);
+        if index == NIL {
+            None
+        } else {
+            Some(&mut self.node_mut(index).value)
+        }
+    }
+
+    pub fn contains_key(&mut self, key: &K) -> bool {
+        self.access(key) != NIL
+    }
+
+    // ordinary bst descent, readers sharing the tree can use it at once
+    pub fn get_no_splay(&self, key: &K) -> Option<&V> {
+        self.value(self.find(key).0)
+    }
+
+    pub fn contains_key_no_splay(&self, key: &K) -> bool {
+        self.get_no_splay(key).is_some()
+    }
+
+    // handle of key without splaying it
+    pub fn handle(&self, key: &K) -> Option<Handle> {
+        let (index, _) = self.find(key);
+        if index == NIL {
+            None
+        } else {
+            Some(Handle(index, self.generations[index as usize]))
+        }
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        if !self.splay_to_root(key) {
+            return None;
+        }
+
+        // splaying key inside the left half lifts its largest node, which
+        // has no right child yet, then the slot of the removed node goes back
+        // to the free list
+        let removed = self.root;
+        let left = self.node(removed).left;
+        let right = self.node(removed).right;
+        if left == NIL {
+            self.set_root(right);
+        } else {
+            self.set_root(left);
+            self.splay_key(key);
+            let root = self.root;
+            self.set_right(root, right);
+        }
+        Some(self.release(removed).value)
+    }
+
+    // index of key, NIL if missing, and how many links lie above it
+    pub(crate) fn find(&self, key: &K) -> (u32, usize) {
+        let mut current = self.root;
+        let mut depth = 0;
+        while current != NIL {
+            let node = self.node(current);
+            let next = match key.cmp(&node.key) {
+                Ordering::Equal => return (current, depth),
+                Ordering::Less => node.left,
+                Ordering::Greater => node.right,
+            };
+            if next == NIL {
+                break;
+            }
+            current = next;
+            depth += 1;
+        }
+        (NIL, depth)
+    }
+
+    // look key up and splay it if the policy asks for it, the index of its
+    // node stays the same either way
+    fn access(&mut self, key: &K) -> u32 {
+        let access = self.accesses;
+        self.accesses = self.accesses.wrapping_add(1);
+        if self.policy == SplayPolicy::Always {
+            return if self.splay_to_root(key) {
+                self.root
+            } else {
+                NIL
+            };
+        }
+        let (index, depth) = self.find(key);
+        if self.policy.should_splay(access, depth) {
+            self.splay_key(key);
+        }
+        index
+    }
+
+    pub(crate) fn splay_to_root(&mut self, key: &K) -> bool {
+        self.splay_key(key);
+        self.root != NIL && self.node(self.root).key == *key
+    }
+
+    fn splay_key(&mut self, key: &K) {
+        if self.root == NIL {
+            return;
+        }
+        match self.strategy {
+            SplayStrategy::TopDown => self.splay_top_down(key),
+            SplayStrategy::BottomUp => {
+                // a missing key splays the last node on its path, which is
+                // its neighbour like with the top-down splay
+                let mut current = self.root;
+                loop {
+                    let node = self.node(current);
+                    let next = match key.cmp(&node.key) {
+                        Ordering::Equal => break,
+                        Ordering::Less => node.left,
+                        Ordering::Greater => node.right,
+                    };
+                    if next == NIL {
+                        break;
+                    }
+                    current = next;
+                }
+                self.splay_node(current);
+            }
+        }
+    }
+
+    // top-down splay, the left and right trees are built by keeping their
+    // last nodes, so linking is constant work
+    fn splay_top_down(&mut self, key: &K) {
+        let mut left_root = NIL;
+        let mut left_tail = NIL;
+        let mut right_root = NIL;
+        let mut right_tail = NIL;
+        let mut current = self.root;
+
+        loop {
+            match key.cmp(&self.node(current).key) {
+                Ordering::Less => {
+                    let mut left = self.node(current).left;
+                    if left == NIL {
+                        break;
+                    }
+                    if *key < self.node(left).key {
+                        // zig zig, rotate right first
+                        let left_right = self.node(left).right;
+                        self.set_left(current, left_right);
+                        self.set_right(left, current);
+                        current = left;
+                        left = self.node(current).left;
+                        if left == NIL {
+                            break;
+                        }
+                    }
+                    // link current to the right tree
+                    if right_tail == NIL {
+                        right_root = current;
+                    } else {
+                        self.set_left(right_tail, current);
+                    }
+                    right_tail = current;
+                    current = left;
+                }
+                Ordering::Greater => {
+                    let mut right = self.node(current).right;
+                    if right == NIL {
+                        break;
+                    }
+                    if *key > self.node(right).key {
+                        // zig zig, rotate left first
+                        let right_left = self.node(right).left;
+                        self.set_right(current, right_left);
+                        self.set_left(right, current);
+                        current = right;
+                        right = self.node(current).right;
+                        if right == NIL {
+                            break;
+                        }
+                    }
+                    // link current to the left tree
+                    if left_tail == NIL {
+                        left_root = current;
+                    } else {
+                        self.set_right(left_tail, current);
+                    }
+                    left_tail = current;
+                    current = right;
+                }
+                Ordering::Equal => break,
+            }
+        }
+
+        // NOTE: merge the left and right trees with current
+        let (current_left, current_right) = {
+            let node = self.node(current);
+            (node.left, node.right)
+        };
+        if left_tail == NIL {
+            left_root = current_left;
+        } else {
+            self.set_right(left_tail, current_left);
+        }
+        if right_tail == NIL {
+            right_root = current_right;
+        } else {
+            self.set_left(right_tail, current_right);
+        }
+        self.set_left(current, left_root);
+        self.set_right(current, right_root);
+        self.set_root(current);
+    }
+}
+
+impl<K: Ord, V> Splayable<K> for ArenaSplayTree<K, V> {
+    fn splay(&mut self, key: K) {
+        self.access(&key);
+    }
+}
+
+pub struct Iter<'a, K, V = ()> {
+    tree: &'a ArenaSplayTree<K, V>,
+    stack: Vec<u32>,
+}
+
+impl<'a, K, V> Iter<'a, K, V> {
+    fn push_left_spine(&mut self, mut current: u32) {
+        while current != NIL {
+            self.stack.push(current);
+            current = self.tree.node(current).left;
+        }
+    }
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let tree = self.tree;
+        let node = tree.node(self.stack.pop()?);
+        self.push_left_spine(node.right);
+        Some((&node.key, &node.value))
+    }
+}
+
+impl<'a, K, V> IntoIterator for &'a ArenaSplayTree<K, V> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
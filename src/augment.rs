@@ -0,0 +1,130 @@
+use crate::augment::sealed::Token;
+use crate::iter::above_lower;
+use crate::iter::below_upper;
+use crate::Compare;
+use crate::Node;
+use crate::SplayNode;
+use crate::SplayTree;
+
+use std::cell::RefCell;
+use std::fmt::Debug;
+use std::mem;
+use std::ops::RangeBounds;
+use std::rc::Rc;
+
+pub(crate) mod sealed {
+    // only the crate can build a Token, which keeps the lazy hooks of Augment
+    // out of reach of other summaries
+    pub struct Token;
+}
+
+// summary kept for every subtree, such as a sum, a minimum or a hash
+//
+// combine has to be associative in the sense that folding the keys of a
+// subtree in order gives the same summary however the subtree is shaped
+//
+// summaries of this crate may also carry lazy tags for their subtree, such as
+// the reversal of a SplaySequence. the hooks for them take a Token only the
+// crate can name, so other summaries never have tags and every path may read
+// children directly. SplaySequence splays by index before it looks below a
+// node, and that splay pushes the tags down on the way
+pub trait Augment<K, V>: Sized {
+    fn combine(left: Option<&Self>, key: &K, value: &V, right: Option<&Self>) -> Self;
+
+    #[doc(hidden)]
+    fn lazy(_: Token) -> bool {
+        false
+    }
+
+    // hand the lazy tags of node to its children
+    #[doc(hidden)]
+    fn push_down(_node: &mut Node<K, V, Self>, _: Token) {}
+}
+
+impl<K, V> Augment<K, V> for () {
+    fn combine(_: Option<&()>, _: &K, _: &V, _: Option<&()>) {}
+}
+
+// summary of the subtree rooted at node, from the summaries of its children
+fn subtree_summary<K, V, A: Augment<K, V>>(node: &Rc<RefCell<Node<K, V, A>>>) -> A {
+    let node = node.borrow();
+    let left = node.left.as_ref().map(|left| left.borrow());
+    let right = node.right.as_ref().map(|right| right.borrow());
+    A::combine(
+        left.as_ref().map(|left| &left.summary),
+        &node.key,
+        &node.value,
+        right.as_ref().map(|right| &right.summary),
+    )
+}
+
+// number of keys of node for which before holds, before has to hold for a
+// prefix of the keys in order
+fn count_before<K, V, A, F: Fn(&K) -> bool>(node: &SplayNode<K, V, A>, before: F) -> usize {
+    let mut count = 0;
+    let mut current = node.clone();
+    while let Some(node) = current {
+        let node = node.borrow();
+        if before(&node.key) {
+            count += node.left_size() + 1;
+            current = node.right.clone();
+        } else {
+            current = node.left.clone();
+        }
+    }
+    count
+}
+
+impl<K: Clone + Debug, V, A: Augment<K, V>, C: Compare<K> + Clone> SplayTree<K, V, A, C> {
+    // summary of every key inside range, None if the range is empty
+    pub fn fold_range<R: RangeBounds<K>>(&mut self, range: R) -> Option<A> {
+        let lower = range.start_bound().cloned();
+        let upper = range.end_bound().cloned();
+        let len = self.len();
+
+        // the range covers the positions first..last. splaying both bounds
+        // first keeps the two counting walks short
+        self.splay_range(&lower, &upper);
+        let cmp = self.comparator();
+        let first = count_before(&self.root, |key| !above_lower(&lower, key, cmp));
+        let last = count_before(&self.root, |key| below_upper(&upper, key, cmp));
+        if first >= last {
+            return None;
+        }
+        if first == 0 && last == len {
+            return self.root.as_ref().map(subtree_summary);
+        }
+
+        // NOTE: splay the node before the range to the root and the node
+        // after it to the root of the right subtree, which leaves the range
+        // as one subtree hanging between them
+        //
+        //        P
+        //       / \
+        //      a   S
+        //         / \
+        //     range  b
+        //
+        // without a node before the range S is the root and the range its
+        // left subtree, without one after it the range is the right subtree
+        // of P
+        if first == 0 {
+            self.splay_at(last);
+            let root = self.root.as_ref().unwrap().borrow();
+            return root.left.as_ref().map(subtree_summary);
+        }
+        self.splay_at(first - 1);
+        let root = self.root.clone().unwrap();
+        if last == len {
+            return root.borrow().right.as_ref().map(subtree_summary);
+        }
+        let mut right_tree = self.with_root(mem::take(&mut root.borrow_mut().right));
+        right_tree.splay_at(last - first);
+        let summary = {
+            let successor = right_tree.root.as_ref().unwrap().borrow();
+            successor.left.as_ref().map(subtree_summary)
+        };
+        root.borrow_mut().right = mem::take(&mut right_tree.root);
+        summary
+    }
+}
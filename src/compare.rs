@@ -0,0 +1,23 @@
+use std::cmp::Ordering;
+
+// ordering of the keys of a tree, every comparison of keys goes through it so
+// it has to be a total order
+pub trait Compare<K: ?Sized> {
+    fn compare(&self, left: &K, right: &K) -> Ordering;
+}
+
+// the Ord ordering of the keys, used unless a tree is given a comparator
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Natural;
+
+impl<K: Ord + ?Sized> Compare<K> for Natural {
+    fn compare(&self, left: &K, right: &K) -> Ordering {
+        left.cmp(right)
+    }
+}
+
+impl<K: ?Sized, F: Fn(&K, &K) -> Ordering> Compare<K> for F {
+    fn compare(&self, left: &K, right: &K) -> Ordering {
+        self(left, right)
+    }
+}
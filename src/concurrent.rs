@@ -0,0 +1,165 @@
+use crate::Access;
+use crate::ArenaSplayTree;
+use crate::SplayPolicy;
+
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::RwLock;
+use std::sync::RwLockReadGuard;
+use std::sync::RwLockWriteGuard;
+
+// splay tree that can be shared between threads. the nodes live in an arena,
+// so there is no Rc and the tree is Send + Sync whenever its keys and values
+// are. splaying rewrites links even when a key is only read, so an access
+// that splays takes the write lock, while Access::NoSplay walks the tree
+// under the read lock and lets readers run side by side. with a policy other
+// than SplayPolicy::Always, splaying accesses that the policy skips are read
+// under the shared lock too. the policy is the one of the wrapped tree
+#[derive(Debug)]
+pub struct ConcurrentSplayTree<K, V = ()> {
+    tree: RwLock<ArenaSplayTree<K, V>>,
+    policy: SplayPolicy,
+    accesses: AtomicUsize,
+}
+
+impl<K, V> Default for ConcurrentSplayTree<K, V> {
+    fn default() -> Self {
+        ConcurrentSplayTree::from(ArenaSplayTree::default())
+    }
+}
+
+impl<K, V> ConcurrentSplayTree<K, V> {
+    pub fn with_policy(policy: SplayPolicy) -> Self {
+        ConcurrentSplayTree::from(ArenaSplayTree::with_policy(policy))
+    }
+
+    pub fn policy(&self) -> SplayPolicy {
+        self.policy
+    }
+
+    pub fn len(&self) -> usize {
+        self.read().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.read().is_empty()
+    }
+
+    pub fn clear(&self) {
+        self.write().clear();
+    }
+
+    pub fn into_inner(self) -> ArenaSplayTree<K, V> {
+        self.tree.into_inner().unwrap()
+    }
+
+    // the guards panic if a thread panicked while holding the lock, the
+    // links may be half rotated at that point
+    fn read(&self) -> RwLockReadGuard<'_, ArenaSplayTree<K, V>> {
+        self.tree.read().unwrap()
+    }
+
+    fn write(&self) -> RwLockWriteGuard<'_, ArenaSplayTree<K, V>> {
+        self.tree.write().unwrap()
+    }
+}
+
+impl<K: Ord, V> ConcurrentSplayTree<K, V> {
+    pub fn new() -> Self {
+        ConcurrentSplayTree::default()
+    }
+
+    pub fn insert(&self, key: K, value: V) -> Option<V> {
+        self.write().insert(key, value)
+    }
+
+    pub fn remove(&self, key: &K) -> Option<V> {
+        self.write().remove(key)
+    }
+
+    // the value is cloned since it can't outlive the lock guard
+    pub fn get(&self, key: &K) -> Option<V>
+    where
+        V: Clone,
+    {
+        self.get_with(key, Access::Splay)
+    }
+
+    pub fn get_with(&self, key: &K, access: Access) -> Option<V>
+    where
+        V: Clone,
+    {
+        self.lookup(key, access, |value| value.cloned())
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.contains_key_with(key, Access::Splay)
+    }
+
+    pub fn contains_key_with(&self, key: &K, access: Access) -> bool {
+        self.lookup(key, access, |value| value.is_some())
+    }
+
+    // change the value in place, returns false if key is missing. nodes keep
+    // their index when splayed, so the value is found before the splay
+    pub fn update<F: FnOnce(&mut V)>(&self, key: &K, f: F) -> bool {
+        let mut tree = self.write();
+        let (index, depth) = tree.find(key);
+        if self.splays(Access::Splay, depth) {
+            tree.splay_to_root(key);
+        }
+        match tree.value_mut(index) {
+            Some(value) => {
+                f(value);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn splay(&self, key: &K) {
+        self.lookup(key, Access::Splay, |_| ());
+    }
+
+    // NOTE: the key is first looked up under the read lock, only when the
+    // policy picks this access is the write lock taken to splay it. another
+    // writer may run in between, so the splay looks the key up again
+    fn lookup<R, F: Fn(Option<&V>) -> R>(&self, key: &K, access: Access, f: F) -> R {
+        if access == Access::Splay && self.policy == SplayPolicy::Always {
+            return self.splayed(key, f);
+        }
+        {
+            let tree = self.read();
+            let (index, depth) = tree.find(key);
+            if !self.splays(access, depth) {
+                return f(tree.value(index));
+            }
+        }
+        self.splayed(key, f)
+    }
+
+    fn splays(&self, access: Access, depth: usize) -> bool {
+        // only sampling reads the counter, it is shared by every thread
+        let count = match self.policy {
+            SplayPolicy::Sampled(_) => self.accesses.fetch_add(1, Ordering::Relaxed),
+            _ => 0,
+        };
+        access == Access::Splay && self.policy.should_splay(count, depth)
+    }
+
+    fn splayed<R, F: Fn(Option<&V>) -> R>(&self, key: &K, f: F) -> R {
+        let mut tree = self.write();
+        tree.splay_to_root(key);
+        f(tree.get_no_splay(key))
+    }
+}
+
+impl<K, V> From<ArenaSplayTree<K, V>> for ConcurrentSplayTree<K, V> {
+    fn from(tree: ArenaSplayTree<K, V>) -> Self {
+        ConcurrentSplayTree {
+            policy: tree.policy(),
+            tree: RwLock::new(tree),
+            accesses: AtomicUsize::new(0),
+        }
+    }
+}
@@ -0,0 +1,149 @@
+use crate::Augment;
+use crate::Compare;
+use crate::Natural;
+use crate::Node;
+use crate::SplayTree;
+
+use std::cell::Ref;
+use std::cell::RefMut;
+use std::fmt::Debug;
+use std::mem;
+use std::ops::Deref;
+use std::ops::DerefMut;
+
+// every entry is created after a single splay, so an occupied entry always
+// sits at the root of its tree and a vacant entry is inserted as the root.
+// values are handed out as ValueGuard, which folds a changed value into the
+// root summary as soon as the borrow ends
+pub enum Entry<'a, K, V, A = (), C = Natural> {
+    Occupied(OccupiedEntry<'a, K, V, A, C>),
+    Vacant(VacantEntry<'a, K, V, A, C>),
+}
+
+pub struct OccupiedEntry<'a, K, V, A = (), C = Natural> {
+    tree: &'a mut SplayTree<K, V, A, C>,
+}
+
+pub struct VacantEntry<'a, K, V, A = (), C = Natural> {
+    tree: &'a mut SplayTree<K, V, A, C>,
+    key: K,
+}
+
+// mutable borrow of the value at the root of a tree
+pub struct ValueGuard<'a, K, V, A: Augment<K, V> = ()> {
+    node: RefMut<'a, Node<K, V, A>>,
+}
+
+impl<'a, K, V, A: Augment<K, V>> Deref for ValueGuard<'a, K, V, A> {
+    type Target = V;
+
+    fn deref(&self) -> &V {
+        &self.node.value
+    }
+}
+
+impl<'a, K, V, A: Augment<K, V>> DerefMut for ValueGuard<'a, K, V, A> {
+    fn deref_mut(&mut self) -> &mut V {
+        &mut self.node.value
+    }
+}
+
+// the root was pushed down by the splay that found it, so update may run
+impl<'a, K, V, A: Augment<K, V>> Drop for ValueGuard<'a, K, V, A> {
+    fn drop(&mut self) {
+        self.node.update();
+    }
+}
+
+impl<K: Clone + Debug, V, A: Augment<K, V>, C: Compare<K> + Clone> SplayTree<K, V, A, C> {
+    pub fn entry(&mut self, key: K) -> Entry<'_, K, V, A, C> {
+        if self.splay_to_root(&key) {
+            Entry::Occupied(OccupiedEntry { tree: self })
+        } else {
+            Entry::Vacant(VacantEntry { tree: self, key })
+        }
+    }
+}
+
+impl<'a, K: Clone + Debug, V, A: Augment<K, V>, C: Compare<K> + Clone> Entry<'a, K, V, A, C> {
+    pub fn or_insert(self, default: V) -> ValueGuard<'a, K, V, A> {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(default),
+        }
+    }
+
+    pub fn or_insert_with<F: FnOnce() -> V>(self, default: F) -> ValueGuard<'a, K, V, A> {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(default()),
+        }
+    }
+
+    pub fn and_modify<F: FnOnce(&mut V)>(self, f: F) -> Self {
+        match self {
+            Entry::Occupied(mut entry) => {
+                f(&mut *entry.get_mut());
+                Entry::Occupied(entry)
+            }
+            Entry::Vacant(entry) => Entry::Vacant(entry),
+        }
+    }
+
+    pub fn key(&self) -> K {
+        match self {
+            Entry::Occupied(entry) => entry.key(),
+            Entry::Vacant(entry) => entry.key.clone(),
+        }
+    }
+}
+
+impl<'a, K: Clone + Debug, V: Default, A: Augment<K, V>, C: Compare<K> + Clone>
+    Entry<'a, K, V, A, C>
+{
+    pub fn or_default(self) -> ValueGuard<'a, K, V, A> {
+        self.or_insert_with(V::default)
+    }
+}
+
+impl<'a, K: Clone + Debug, V, A: Augment<K, V>, C: Compare<K> + Clone>
+    OccupiedEntry<'a, K, V, A, C>
+{
+    pub fn key(&self) -> K {
+        self.tree.root.as_ref().unwrap().borrow().key.clone()
+    }
+
+    pub fn get(&self) -> Ref<'_, V> {
+        let root = self.tree.root.as_ref().unwrap();
+        Ref::map(root.borrow(), |node| &node.value)
+    }
+
+    pub fn get_mut(&mut self) -> ValueGuard<'_, K, V, A> {
+        ValueGuard {
+            node: self.tree.root.as_ref().unwrap().borrow_mut(),
+        }
+    }
+
+    pub fn into_mut(self) -> ValueGuard<'a, K, V, A> {
+        ValueGuard {
+            node: self.tree.root.as_ref().unwrap().borrow_mut(),
+        }
+    }
+
+    pub fn insert(&mut self, value: V) -> V {
+        mem::replace(&mut *self.get_mut(), value)
+    }
+}
+
+impl<'a, K: Clone + Debug, V, A: Augment<K, V>, C: Compare<K> + Clone> VacantEntry<'a, K, V, A, C> {
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    pub fn insert(self, value: V) -> ValueGuard<'a, K, V, A> {
+        self.tree.insert_root(Node::with_value(self.key, value));
+        ValueGuard {
+            node: self.tree.root.as_ref().unwrap().borrow_mut(),
+        }
+    }
+}
@@ -0,0 +1,24 @@
+use std::error::Error;
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SplayError {
+    // the tree has no node to splay
+    EmptyTree,
+    // no node holds the key
+    KeyNotFound,
+    // the key is already there and the insertion rejects duplicates
+    DuplicateKey,
+}
+
+impl fmt::Display for SplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SplayError::EmptyTree => write!(f, "splay tree is empty"),
+            SplayError::KeyNotFound => write!(f, "key is not in the splay tree"),
+            SplayError::DuplicateKey => write!(f, "key is already in the splay tree"),
+        }
+    }
+}
+
+impl Error for SplayError {}
@@ -0,0 +1,309 @@
+use crate::augment::sealed::Token;
+use crate::Augment;
+use crate::Compare;
+use crate::Natural;
+use crate::Node;
+use crate::SplayNode;
+use crate::SplayTree;
+
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::fmt::Debug;
+use std::marker::PhantomData;
+use std::mem;
+use std::ops::Bound;
+use std::ops::RangeBounds;
+use std::rc::Rc;
+
+type Spine<K, V, A> = Vec<Rc<RefCell<Node<K, V, A>>>>;
+
+// in-order walk that only follows links, the tree is never splayed while an
+// iterator borrows it
+pub struct Iter<'a, K, V = (), A = ()> {
+    // left spine for walking forward, right spine for walking backward
+    front: Spine<K, V, A>,
+    back: Spine<K, V, A>,
+    remaining: usize,
+    marker: PhantomData<&'a SplayTree<K, V, A>>,
+}
+
+pub struct Keys<'a, K, V = (), A = ()> {
+    inner: Iter<'a, K, V, A>,
+}
+
+// in-order walk limited to a range of keys, it stops once the two ends meet
+pub struct Range<'a, K, V = (), A = (), C = Natural> {
+    front: Spine<K, V, A>,
+    back: Spine<K, V, A>,
+    front_last: SplayNode<K, V, A>,
+    back_last: SplayNode<K, V, A>,
+    lower: Bound<K>,
+    upper: Bound<K>,
+    cmp: &'a C,
+    marker: PhantomData<&'a SplayTree<K, V, A, C>>,
+}
+
+// lazy tags are pushed down on the way, which leaves the order unchanged.
+// trees without tags are only borrowed, so callers may keep nodes borrowed
+fn push_down<K, V, A: Augment<K, V>>(node: &Rc<RefCell<Node<K, V, A>>>) {
+    if A::lazy(Token) {
+        node.borrow_mut().push_down();
+    }
+}
+
+fn push_left_spine<K, V, A: Augment<K, V>>(
+    stack: &mut Spine<K, V, A>,
+    mut current: SplayNode<K, V, A>,
+) {
+    while let Some(node) = current {
+        push_down(&node);
+        current = node.borrow().left.clone();
+        stack.push(node);
+    }
+}
+
+fn push_right_spine<K, V, A: Augment<K, V>>(
+    stack: &mut Spine<K, V, A>,
+    mut current: SplayNode<K, V, A>,
+) {
+    while let Some(node) = current {
+        push_down(&node);
+        current = node.borrow().right.clone();
+        stack.push(node);
+    }
+}
+
+impl<'a, K, V, A: Augment<K, V>> Iter<'a, K, V, A> {
+    pub(crate) fn new(root: &'a SplayNode<K, V, A>) -> Self {
+        let mut front = Vec::new();
+        let mut back = Vec::new();
+        push_left_spine(&mut front, root.clone());
+        push_right_spine(&mut back, root.clone());
+        Iter {
+            front,
+            back,
+            remaining: root.as_ref().map_or(0, |root| root.borrow().size),
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<'a, K, V, A: Augment<K, V>> Iterator for Iter<'a, K, V, A> {
+    type Item = Rc<RefCell<Node<K, V, A>>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+
+        let node = self.front.pop()?;
+        let right = node.borrow().right.clone();
+        push_left_spine(&mut self.front, right);
+        Some(node)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<'a, K, V, A: Augment<K, V>> DoubleEndedIterator for Iter<'a, K, V, A> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+
+        let node = self.back.pop()?;
+        let left = node.borrow().left.clone();
+        push_right_spine(&mut self.back, left);
+        Some(node)
+    }
+}
+
+impl<'a, K, V, A: Augment<K, V>> ExactSizeIterator for Iter<'a, K, V, A> {}
+
+impl<'a, K: Clone, V, A: Augment<K, V>> Iterator for Keys<'a, K, V, A> {
+    type Item = K;
+
+    fn next(&mut self) -> Option<K> {
+        self.inner.next().map(|node| node.borrow().key.clone())
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, K: Clone, V, A: Augment<K, V>> DoubleEndedIterator for Keys<'a, K, V, A> {
+    fn next_back(&mut self) -> Option<K> {
+        self.inner.next_back().map(|node| node.borrow().key.clone())
+    }
+}
+
+impl<'a, K: Clone, V, A: Augment<K, V>> ExactSizeIterator for Keys<'a, K, V, A> {}
+
+pub(crate) fn above_lower<K, C: Compare<K>>(lower: &Bound<K>, key: &K, cmp: &C) -> bool {
+    match lower {
+        Bound::Included(lower) => cmp.compare(key, lower) != Ordering::Less,
+        Bound::Excluded(lower) => cmp.compare(key, lower) == Ordering::Greater,
+        Bound::Unbounded => true,
+    }
+}
+
+pub(crate) fn below_upper<K, C: Compare<K>>(upper: &Bound<K>, key: &K, cmp: &C) -> bool {
+    match upper {
+        Bound::Included(upper) => cmp.compare(key, upper) != Ordering::Greater,
+        Bound::Excluded(upper) => cmp.compare(key, upper) == Ordering::Less,
+        Bound::Unbounded => true,
+    }
+}
+
+fn bound_key<K>(bound: &Bound<K>) -> Option<&K> {
+    match bound {
+        Bound::Included(key) | Bound::Excluded(key) => Some(key),
+        Bound::Unbounded => None,
+    }
+}
+
+fn is_same<K, V, A>(node: &Rc<RefCell<Node<K, V, A>>>, other: &SplayNode<K, V, A>) -> bool {
+    other.as_ref().is_some_and(|other| Rc::ptr_eq(node, other))
+}
+
+impl<'a, K, V, A: Augment<K, V>, C: Compare<K>> Range<'a, K, V, A, C> {
+    pub(crate) fn new(
+        root: &'a SplayNode<K, V, A>,
+        lower: Bound<K>,
+        upper: Bound<K>,
+        cmp: &'a C,
+    ) -> Self {
+        let mut front = Vec::new();
+        let mut back = Vec::new();
+
+        // keep every node on the search path that could still be the first
+        // (or last) key inside the range
+        let mut current = root.clone();
+        while let Some(node) = current {
+            push_down(&node);
+            if above_lower(&lower, &node.borrow().key, cmp) {
+                current = node.borrow().left.clone();
+                front.push(node);
+            } else {
+                current = node.borrow().right.clone();
+            }
+        }
+        let mut current = root.clone();
+        while let Some(node) = current {
+            push_down(&node);
+            if below_upper(&upper, &node.borrow().key, cmp) {
+                current = node.borrow().right.clone();
+                back.push(node);
+            } else {
+                current = node.borrow().left.clone();
+            }
+        }
+
+        Range {
+            front,
+            back,
+            front_last: None,
+            back_last: None,
+            lower,
+            upper,
+            cmp,
+            marker: PhantomData,
+        }
+    }
+
+    fn finish(&mut self) {
+        self.front.clear();
+        self.back.clear();
+    }
+}
+
+impl<'a, K, V, A: Augment<K, V>, C: Compare<K>> Iterator for Range<'a, K, V, A, C> {
+    type Item = Rc<RefCell<Node<K, V, A>>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.front.pop()?;
+        if !below_upper(&self.upper, &node.borrow().key, self.cmp)
+            || is_same(&node, &self.back_last)
+        {
+            self.finish();
+            return None;
+        }
+
+        let right = node.borrow().right.clone();
+        push_left_spine(&mut self.front, right);
+        self.front_last = Some(node.clone());
+        Some(node)
+    }
+}
+
+impl<'a, K, V, A: Augment<K, V>, C: Compare<K>> DoubleEndedIterator for Range<'a, K, V, A, C> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let node = self.back.pop()?;
+        if !above_lower(&self.lower, &node.borrow().key, self.cmp)
+            || is_same(&node, &self.front_last)
+        {
+            self.finish();
+            return None;
+        }
+
+        let left = node.borrow().left.clone();
+        push_right_spine(&mut self.back, left);
+        self.back_last = Some(node.clone());
+        Some(node)
+    }
+}
+
+impl<K: Clone + Debug, V, A: Augment<K, V>, C: Compare<K> + Clone> SplayTree<K, V, A, C> {
+    // splay the lower bound to the root and the upper bound to the root of
+    // its right subtree, so the keys of the range hang right below them
+    pub(crate) fn splay_range(&mut self, lower: &Bound<K>, upper: &Bound<K>) {
+        let lower = bound_key(lower);
+        let upper = bound_key(upper);
+        if let Some(lower) = lower {
+            self.splay_key(lower);
+        }
+
+        match (lower, upper) {
+            (Some(_), Some(upper)) => {
+                if let Some(ref root) = self.root {
+                    let mut right_tree = self.with_root(mem::take(&mut root.borrow_mut().right));
+                    right_tree.splay_key(upper);
+                    root.borrow_mut().right = mem::take(&mut right_tree.root);
+                }
+            }
+            (None, Some(upper)) => self.splay_key(upper),
+            _ => {}
+        }
+    }
+
+    pub fn range<R: RangeBounds<K>>(&mut self, range: R) -> Range<'_, K, V, A, C> {
+        let lower = range.start_bound().cloned();
+        let upper = range.end_bound().cloned();
+        self.splay_range(&lower, &upper);
+        Range::new(&self.root, lower, upper, self.comparator())
+    }
+
+    pub fn iter(&self) -> Iter<'_, K, V, A> {
+        Iter::new(&self.root)
+    }
+
+    pub fn keys(&self) -> Keys<'_, K, V, A> {
+        Keys { inner: self.iter() }
+    }
+}
+
+impl<'a, K: Clone + Debug, V, A: Augment<K, V>, C: Compare<K> + Clone> IntoIterator
+    for &'a SplayTree<K, V, A, C>
+{
+    type Item = Rc<RefCell<Node<K, V, A>>>;
+    type IntoIter = Iter<'a, K, V, A>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
@@ -1,8 +1,40 @@
+pub mod arena;
+pub mod augment;
+pub mod compare;
+pub mod concurrent;
+pub mod entry;
+pub mod error;
+pub mod iter;
+pub mod map;
+pub mod multiset;
 pub mod node;
+pub mod owned;
+pub mod persistent;
+pub mod policy;
+pub mod sequence;
 pub mod tree;
 
+pub use arena::ArenaSplayTree;
+pub use arena::SplayStrategy;
+pub use augment::Augment;
+pub use compare::Compare;
+pub use compare::Natural;
+pub use concurrent::ConcurrentSplayTree;
+pub use entry::Entry;
+pub use entry::ValueGuard;
+pub use error::SplayError;
+pub use map::SplayMap;
+pub use multiset::SplayMultiset;
 pub use node::Node;
 pub use node::SplayNode;
+pub use owned::OwnedSplayTree;
+pub use persistent::PersistentSplayTree;
+pub use policy::SplayPolicy;
+pub use sequence::Lazy;
+pub use sequence::SplaySequence;
+pub use tree::Access;
+pub use tree::DuplicatePolicy;
+pub use tree::InsertOutcome;
 pub use tree::SplayTree;
 pub use tree::Splayable;
 
@@ -10,6 +42,14 @@ pub use tree::Splayable;
 mod tests {
     use super::*;
 
+    // step of the linear congruential generator the randomized tests use
+    fn next_seed(seed: &mut u64) -> u64 {
+        *seed = seed
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        *seed
+    }
+
     #[test]
     fn insert_example() {
         let mut splay_tree = SplayTree::new();
@@ -88,4 +128,1190 @@ mod tests {
             assert_eq!(v.as_ref().borrow().key, ans[i]);
         }
     }
+
+    #[test]
+    fn map_example() {
+        let mut splay_map = SplayMap::new();
+        assert_eq!(splay_map.insert(5, "five"), None);
+        assert_eq!(splay_map.insert(2, "two"), None);
+        assert_eq!(splay_map.insert(8, "eight"), None);
+        assert_eq!(splay_map.insert(5, "FIVE"), Some("five"));
+        assert_eq!(*splay_map.get(&5).unwrap(), "FIVE");
+        assert!(splay_map.get(&3).is_none());
+        *splay_map.get_mut(&2).unwrap() = "TWO";
+        assert_eq!(splay_map.remove(&2), Some("TWO"));
+        assert_eq!(splay_map.remove(&2), None);
+        assert!(!splay_map.contains_key(&2));
+        assert!(splay_map.contains_key(&8));
+    }
+
+    #[test]
+    fn map_against_btreemap() {
+        use std::collections::BTreeMap;
+
+        let mut splay_map = SplayMap::new();
+        let mut btree_map = BTreeMap::new();
+        let mut seed: u64 = 3438;
+        for i in 0..2000 {
+            next_seed(&mut seed);
+            let key = (seed >> 33) % 100;
+            match i % 3 {
+                0 => assert_eq!(splay_map.insert(key, i), btree_map.insert(key, i)),
+                1 => assert_eq!(splay_map.remove(&key), btree_map.remove(&key)),
+                _ => assert_eq!(
+                    splay_map.get(&key).map(|v| *v),
+                    btree_map.get(&key).copied()
+                ),
+            }
+        }
+    }
+
+    #[test]
+    fn entry_counting() {
+        let mut splay_map = SplayMap::new();
+        for word in "a b a c b a".split(' ') {
+            *splay_map.entry(word).or_default() += 1;
+        }
+        assert_eq!(*splay_map.get(&"a").unwrap(), 3);
+        assert_eq!(*splay_map.get(&"b").unwrap(), 2);
+        assert_eq!(*splay_map.get(&"c").unwrap(), 1);
+
+        splay_map.entry("c").and_modify(|v| *v *= 10).or_insert(0);
+        splay_map
+            .entry("d")
+            .and_modify(|v| *v *= 10)
+            .or_insert_with(|| 7);
+        assert_eq!(*splay_map.get(&"c").unwrap(), 10);
+        assert_eq!(*splay_map.get(&"d").unwrap(), 7);
+    }
+
+    #[test]
+    fn entry_on_tree() {
+        let mut splay_tree = SplayTree::new();
+        splay_tree.insert(&mut Node::with_value(5, 50));
+        splay_tree.insert(&mut Node::with_value(1, 10));
+        match splay_tree.entry(5) {
+            Entry::Occupied(mut entry) => assert_eq!(entry.insert(55), 50),
+            Entry::Vacant(_) => panic!("5 should be occupied"),
+        }
+        *splay_tree.entry(3).or_insert(30) += 3;
+        assert_eq!(splay_tree.root.as_ref().unwrap().borrow().key, 3);
+        assert_eq!(splay_tree.root.as_ref().unwrap().borrow().value, 33);
+    }
+
+    #[test]
+    fn borrowing_iter() {
+        let mut splay_tree = SplayTree::new();
+        for key in [4, 2, 8, 10, 9, 7, 6] {
+            splay_tree.insert(&mut Node::new(key));
+        }
+        let root_key = splay_tree.root.as_ref().unwrap().borrow().key;
+
+        let mut iter = splay_tree.iter();
+        assert_eq!(iter.len(), 7);
+        assert_eq!(iter.next().unwrap().borrow().key, 2);
+        assert_eq!(iter.next_back().unwrap().borrow().key, 10);
+        assert_eq!(iter.len(), 5);
+        assert_eq!(
+            splay_tree.keys().collect::<Vec<_>>(),
+            [2, 4, 6, 7, 8, 9, 10]
+        );
+        assert_eq!(
+            splay_tree.keys().rev().collect::<Vec<_>>(),
+            [10, 9, 8, 7, 6, 4, 2]
+        );
+        assert_eq!(splay_tree.root.as_ref().unwrap().borrow().key, root_key);
+
+        // nodes may stay borrowed while another iterator walks over them
+        let mut pairs = 0;
+        for a in splay_tree.iter() {
+            let a = a.borrow();
+            for b in splay_tree.iter() {
+                if a.key < b.borrow().key {
+                    pairs += 1;
+                }
+            }
+        }
+        assert_eq!(pairs, 21);
+        let node = splay_tree
+            .iter()
+            .find(|node| node.borrow().key == 2)
+            .unwrap();
+        let _borrowed = node.borrow();
+        assert_eq!(splay_tree.keys().count(), 7);
+    }
+
+    #[test]
+    fn map_iter_mut() {
+        let mut splay_map = SplayMap::new();
+        for key in [3, 1, 2] {
+            splay_map.insert(key, key * 10);
+        }
+        for mut value in splay_map.iter_mut() {
+            let key = *value.key();
+            *value.get_mut() += key;
+        }
+        assert_eq!(
+            splay_map
+                .iter()
+                .map(|entry| (*entry.key(), *entry.get()))
+                .collect::<Vec<_>>(),
+            [(1, 11), (2, 22), (3, 33)]
+        );
+        assert_eq!(
+            splay_map
+                .values()
+                .rev()
+                .map(|value| *value.get())
+                .collect::<Vec<_>>(),
+            [33, 22, 11]
+        );
+        assert_eq!(splay_map.remove(&2), Some(22));
+    }
+
+    #[test]
+    fn range_query() {
+        let mut splay_tree = SplayTree::new();
+        for key in [5, 1, 9, 3, 7, 3, 11] {
+            splay_tree.insert(&mut Node::new(key));
+        }
+        let keys = |range: crate::iter::Range<i32>| {
+            range.map(|node| node.borrow().key).collect::<Vec<_>>()
+        };
+        assert_eq!(keys(splay_tree.range(3..9)), [3, 3, 5, 7]);
+        assert_eq!(keys(splay_tree.range(..=5)), [1, 3, 3, 5]);
+        assert_eq!(keys(splay_tree.range(4..)), [5, 7, 9, 11]);
+        assert_eq!(keys(splay_tree.range(6..7)), []);
+        assert_eq!(
+            splay_tree
+                .range(2..=9)
+                .rev()
+                .map(|node| node.borrow().key)
+                .collect::<Vec<_>>(),
+            [9, 7, 5, 3, 3]
+        );
+        assert_eq!(splay_tree.keys().count(), 7);
+
+        let mut range = splay_tree.range(..);
+        assert_eq!(range.next().unwrap().borrow().key, 1);
+        assert_eq!(range.next_back().unwrap().borrow().key, 11);
+        assert_eq!(range.count(), 5);
+    }
+
+    #[test]
+    fn map_range_mut() {
+        let mut splay_map = SplayMap::new();
+        for key in 0..10 {
+            splay_map.insert(key, 0);
+        }
+        for mut value in splay_map.range_mut(3..6) {
+            value.set(1);
+        }
+        assert_eq!(splay_map.values().map(|value| *value.get()).sum::<i32>(), 3);
+        assert_eq!(
+            splay_map
+                .range(2..=3)
+                .map(|entry| (*entry.key(), *entry.get()))
+                .collect::<Vec<_>>(),
+            [(2, 0), (3, 1)]
+        );
+    }
+
+    #[test]
+    fn split_and_join() {
+        let mut splay_tree = SplayTree::new();
+        for key in [5, 1, 9, 3, 7, 3, 11, 5] {
+            splay_tree.insert(&mut Node::new(key));
+        }
+        let (left, right) = splay_tree.split(5);
+        assert_eq!(left.keys().collect::<Vec<_>>(), [1, 3, 3]);
+        assert_eq!(right.keys().collect::<Vec<_>>(), [5, 5, 7, 9, 11]);
+
+        let mut joined = SplayTree::join(left, right);
+        let tail = joined.split_off(&8);
+        assert_eq!(joined.keys().collect::<Vec<_>>(), [1, 3, 3, 5, 5, 7]);
+        assert_eq!(tail.keys().collect::<Vec<_>>(), [9, 11]);
+
+        let mut other = SplayTree::new();
+        other.insert(&mut Node::new(4));
+        other.insert(&mut Node::new(0));
+        joined.append(&mut other);
+        assert!(other.root.is_none());
+        assert_eq!(joined.keys().collect::<Vec<_>>(), [0, 1, 3, 3, 4, 5, 5, 7]);
+    }
+
+    #[test]
+    fn map_split_off_append() {
+        let mut splay_map = SplayMap::new();
+        for key in 0..6 {
+            splay_map.insert(key, "a");
+        }
+        let mut tail = splay_map.split_off(&3);
+        assert_eq!(splay_map.keys().collect::<Vec<_>>(), [0, 1, 2]);
+        assert_eq!(tail.keys().collect::<Vec<_>>(), [3, 4, 5]);
+
+        let mut other = SplayMap::new();
+        other.insert(2, "b");
+        other.insert(9, "b");
+        tail.append(&mut splay_map);
+        tail.append(&mut other);
+        assert!(splay_map.is_empty() && other.is_empty());
+        assert_eq!(
+            tail.iter()
+                .map(|entry| (*entry.key(), *entry.get()))
+                .collect::<Vec<_>>(),
+            [
+                (0, "a"),
+                (1, "a"),
+                (2, "b"),
+                (3, "a"),
+                (4, "a"),
+                (5, "a"),
+                (9, "b")
+            ]
+        );
+    }
+
+    fn check_sizes<K, V>(node: &SplayNode<K, V>) -> usize {
+        node.as_ref().map_or(0, |node| {
+            let node = node.borrow();
+            let size = check_sizes(&node.left) + check_sizes(&node.right) + 1;
+            assert_eq!(node.size, size);
+            size
+        })
+    }
+
+    #[test]
+    fn order_statistics() {
+        let mut splay_tree = SplayTree::new();
+        let mut sorted = Vec::new();
+        let mut seed: u64 = 3438;
+        for i in 0..1500 {
+            next_seed(&mut seed);
+            let key = (seed >> 33) % 200;
+            let position = sorted.partition_point(|&k| k < key);
+            match i % 5 {
+                0 | 1 => {
+                    splay_tree.insert(&mut Node::new(key));
+                    sorted.insert(position, key);
+                }
+                2 => {
+                    let popped = splay_tree.pop_left_most().map(|node| node.borrow().key);
+                    assert_eq!(popped, (!sorted.is_empty()).then(|| sorted.remove(0)));
+                }
+                3 => assert_eq!(splay_tree.rank(&key), position),
+                _ => {
+                    let index = (seed >> 40) as usize % (sorted.len() + 1);
+                    let selected = splay_tree.select(index).map(|node| node.borrow().key);
+                    assert_eq!(selected, sorted.get(index).copied());
+                }
+            }
+            assert_eq!(check_sizes(&splay_tree.root), sorted.len());
+            assert_eq!(splay_tree.len(), sorted.len());
+        }
+
+        while let Some(node) = splay_tree.pop_left_most() {
+            assert_eq!(node.borrow().key, sorted.remove(0));
+            assert_eq!(check_sizes(&splay_tree.root), sorted.len());
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct SumMax {
+        sum: i64,
+        max: i64,
+    }
+
+    impl Augment<i64, i64> for SumMax {
+        fn combine(left: Option<&Self>, _: &i64, value: &i64, right: Option<&Self>) -> Self {
+            let mut summary = SumMax {
+                sum: *value,
+                max: *value,
+            };
+            for child in left.into_iter().chain(right) {
+                summary.sum += child.sum;
+                summary.max = summary.max.max(child.max);
+            }
+            summary
+        }
+    }
+
+    #[test]
+    fn augmented_fold_range() {
+        let mut splay_tree: SplayTree<i64, i64, SumMax> = SplayTree::default();
+        let mut pairs = Vec::new();
+        let mut seed: u64 = 3438;
+        for i in 0..1000 {
+            next_seed(&mut seed);
+            let key = (seed >> 33) as i64 % 100;
+            let other = (seed >> 45) as i64 % 100;
+            match i % 4 {
+                0 | 1 => {
+                    if pairs.iter().all(|&(k, _)| k != key) {
+                        splay_tree.insert(&mut Node::with_value(key, other));
+                        pairs.push((key, other));
+                    }
+                }
+                2 => {
+                    let popped = splay_tree.pop_left_most().map(|node| node.borrow().key);
+                    assert_eq!(popped, pairs.iter().map(|&(k, _)| k).min());
+                    pairs.retain(|&(k, _)| Some(k) != popped);
+                }
+                _ => {
+                    let (lower, upper) = (key.min(other), key.max(other));
+                    let expected = |inside: &dyn Fn(i64) -> bool| {
+                        let values = pairs.iter().filter(|&&(k, _)| inside(k)).map(|&(_, v)| v);
+                        values.clone().max().map(|max| SumMax {
+                            sum: values.sum(),
+                            max,
+                        })
+                    };
+                    assert_eq!(
+                        splay_tree.fold_range(lower..upper),
+                        expected(&|k| lower <= k && k < upper)
+                    );
+                    assert_eq!(
+                        splay_tree.fold_range(lower..=upper),
+                        expected(&|k| lower <= k && k <= upper)
+                    );
+                    assert_eq!(splay_tree.fold_range(upper..), expected(&|k| upper <= k));
+                    assert_eq!(splay_tree.fold_range(..=lower), expected(&|k| k <= lower));
+                }
+            }
+        }
+        let total = pairs.iter().map(|&(_, v)| v).sum::<i64>();
+        assert_eq!(splay_tree.fold_range(..).map(|s| s.sum), Some(total));
+        assert_eq!(
+            splay_tree
+                .root
+                .as_ref()
+                .map(|root| root.borrow().summary.sum),
+            Some(total)
+        );
+    }
+
+    #[test]
+    fn entry_updates_summary() {
+        let mut splay_tree: SplayTree<i64, i64, SumMax> = SplayTree::default();
+        for key in 0..5 {
+            splay_tree.insert(&mut Node::with_value(key, 1));
+        }
+        let root_summary = |tree: &SplayTree<i64, i64, SumMax>| {
+            let root = tree.root.as_ref().unwrap().borrow();
+            (root.summary.sum, root.summary.max)
+        };
+
+        *splay_tree.entry(2).or_insert(0) += 100;
+        assert_eq!(root_summary(&splay_tree), (105, 101));
+        splay_tree.entry(7).or_insert(10);
+        assert_eq!(root_summary(&splay_tree), (115, 101));
+        splay_tree.entry(3).and_modify(|value| *value = 200);
+        assert_eq!(root_summary(&splay_tree), (314, 200));
+        if let Entry::Occupied(mut entry) = splay_tree.entry(0) {
+            assert_eq!(entry.insert(50), 1);
+        }
+        assert_eq!(root_summary(&splay_tree), (363, 200));
+        assert_eq!(
+            splay_tree.fold_range(..),
+            Some(SumMax { sum: 363, max: 200 })
+        );
+    }
+
+    #[test]
+    fn sequence_by_index() {
+        let mut sequence: SplaySequence<char> = "splay".chars().collect();
+        sequence.insert_at(0, '>');
+        sequence.insert_at(3, '-');
+        sequence.push_back('!');
+        assert_eq!(sequence.iter().collect::<String>(), ">sp-lay!");
+        assert_eq!(*sequence.get(4).unwrap(), 'l');
+        assert_eq!(sequence.remove_at(3), Some('-'));
+        assert_eq!(sequence.remove_at(7), None);
+        *sequence.get_mut(0).unwrap() = '<';
+
+        let (left, right) = sequence.split_at(3);
+        assert_eq!(left.iter().collect::<String>(), "<sp");
+        assert_eq!(right.iter().collect::<String>(), "lay!");
+        let joined = SplaySequence::concat(right, left);
+        assert_eq!(joined.iter().collect::<String>(), "lay!<sp");
+        assert_eq!(joined.len(), 7);
+    }
+
+    #[test]
+    fn sequence_against_vec() {
+        let mut sequence = SplaySequence::new();
+        let mut vec = Vec::new();
+        let mut seed: u64 = 3438;
+        for i in 0..2000 {
+            next_seed(&mut seed);
+            let index = (seed >> 33) as usize % (vec.len() + 1);
+            match i % 4 {
+                0 | 1 => {
+                    sequence.insert_at(index, i);
+                    vec.insert(index, i);
+                }
+                2 => {
+                    let expected = (index < vec.len()).then(|| vec.remove(index));
+                    assert_eq!(sequence.remove_at(index), expected);
+                }
+                _ => assert_eq!(sequence.get(index).map(|v| *v), vec.get(index).copied()),
+            }
+        }
+        assert_eq!(sequence.iter().collect::<Vec<_>>(), vec);
+    }
+
+    #[test]
+    fn lazy_reverse_and_add() {
+        let mut sequence: SplaySequence<i64, Lazy<i64>> = (0..10).collect();
+        sequence.reverse(2..7);
+        sequence.add(..4, 100);
+        let expected = vec![100, 101, 106, 105, 4, 3, 2, 7, 8, 9];
+        assert_eq!(sequence.iter().collect::<Vec<_>>(), expected);
+
+        let mut vec: Vec<i64> = (0..300).collect();
+        let mut sequence: SplaySequence<i64, Lazy<i64>> = vec.iter().cloned().collect();
+        let mut seed: u64 = 3438;
+        for i in 0..2000 {
+            next_seed(&mut seed);
+            let a = (seed >> 33) as usize % (vec.len() + 1);
+            let b = (seed >> 13) as usize % (vec.len() + 1);
+            let (start, end) = (a.min(b), a.max(b));
+            match i % 4 {
+                0 => {
+                    sequence.reverse(start..end);
+                    vec[start..end].reverse();
+                }
+                1 => {
+                    sequence.add(start..end, i);
+                    vec[start..end].iter_mut().for_each(|v| *v += i);
+                }
+                2 => assert_eq!(sequence.get(start).map(|v| *v), vec.get(start).copied()),
+                _ => {
+                    let expected = (start < vec.len()).then(|| vec.remove(start));
+                    assert_eq!(sequence.remove_at(start), expected);
+                }
+            }
+        }
+        assert_eq!(sequence.iter().collect::<Vec<_>>(), vec);
+        vec.reverse();
+        assert_eq!(sequence.iter().rev().collect::<Vec<_>>(), vec);
+    }
+
+    #[test]
+    fn arena_against_btreemap() {
+        use std::collections::BTreeMap;
+
+        let mut tree = ArenaSplayTree::new();
+        let mut model = BTreeMap::new();
+        let mut seed: u64 = 3438;
+        for i in 0..3000 {
+            next_seed(&mut seed);
+            let key = (seed >> 33) % 500;
+            match i % 3 {
+                0 | 1 => assert_eq!(tree.insert(key, i), model.insert(key, i)),
+                _ => assert_eq!(tree.remove(&key), model.remove(&key)),
+            }
+            assert_eq!(tree.get(&key), model.get(&key));
+            assert_eq!(tree.len(), model.len());
+        }
+        assert!(tree.iter().eq(model.iter()));
+
+        // removed slots are reused instead of growing the arena
+        let capacity = tree.capacity();
+        let keys: Vec<u64> = model.keys().cloned().collect();
+        for key in &keys {
+            tree.remove(key);
+        }
+        for key in &keys {
+            tree.insert(*key, 0);
+        }
+        assert_eq!(tree.capacity(), capacity);
+    }
+
+    #[test]
+    fn owned_against_btreeset() {
+        use std::collections::BTreeSet;
+
+        let mut tree = OwnedSplayTree::new();
+        let mut model = BTreeSet::new();
+        let mut seed: u64 = 3438;
+        for i in 0..3000 {
+            next_seed(&mut seed);
+            let key = (seed >> 33) % 500;
+            match i % 3 {
+                0 | 1 => assert_eq!(tree.insert(key), model.insert(key)),
+                _ => assert_eq!(tree.remove(&key), model.take(&key)),
+            }
+            assert_eq!(tree.contains(&key), model.contains(&key));
+            assert_eq!(tree.len(), model.len());
+        }
+        assert!(tree.iter().eq(model.iter()));
+
+        // sorted insertion leaves a single long path to drop
+        let tree: OwnedSplayTree<u32> = (0..200_000).collect();
+        assert_eq!(tree.len(), 200_000);
+    }
+
+    #[test]
+    fn fallible_api() {
+        let mut tree = SplayTree::new();
+        assert_eq!(tree.try_splay(&1), Err(SplayError::EmptyTree));
+        assert_eq!(tree.try_delete(&1), Err(SplayError::EmptyTree));
+
+        for key in [5, 3, 8] {
+            tree.insert(&mut Node::new(key));
+        }
+        assert_eq!(tree.try_delete(&4), Err(SplayError::KeyNotFound));
+        assert_eq!(tree.try_splay(&4), Ok(()));
+        assert_eq!(tree.try_delete(&3), Ok(()));
+        assert_eq!(tree.keys().collect::<Vec<_>>(), vec![5, 8]);
+
+        // a root without a left child is deleted like any other node
+        tree.splay(5);
+        tree.delete(5);
+        tree.delete(8);
+        assert!(tree.is_empty());
+    }
+
+    #[test]
+    fn inconsistent_order_keeps_nodes() {
+        use std::cmp::Ordering;
+
+        // 5 and 6 both claim to be larger than the other
+        let cmp = |a: &u32, b: &u32| match (*a, *b) {
+            (5, 6) | (6, 5) => Ordering::Greater,
+            (a, b) => a.cmp(&b),
+        };
+
+        // insertion would have to compare 5 and 6, so link the tree by hand
+        //
+        //       8
+        //      /
+        //     6
+        //    /
+        //   4
+        //    \
+        //     5
+        let nodes: Vec<_> = [8, 6, 4, 5].iter().map(|&key| Node::new(key)).collect();
+        nodes[2].borrow_mut().right = Some(nodes[3].clone());
+        nodes[1].borrow_mut().left = Some(nodes[2].clone());
+        nodes[0].borrow_mut().left = Some(nodes[1].clone());
+        for node in nodes.iter().rev() {
+            node.borrow_mut().update();
+        }
+        let mut tree = SplayTree::with_comparator(cmp);
+        tree.root = Some(nodes[0].clone());
+
+        assert_eq!(tree.try_splay(&5), Ok(()));
+        assert_eq!(tree.len(), 4);
+        assert_eq!(tree.keys().collect::<Vec<_>>(), vec![4, 5, 6, 8]);
+    }
+
+    #[test]
+    fn remove_returns_element() {
+        let mut tree = SplayTree::new();
+        let mut kept = Node::with_value(7, "seven");
+        tree.insert(&mut kept);
+        for key in [3, 7, 9] {
+            tree.insert(&mut Node::with_value(key, "other"));
+        }
+        assert_eq!(tree.remove(&4), None);
+        assert_eq!(tree.remove(&3), Some(3));
+        assert_eq!(
+            tree.take_node(&9).map(|node| node.borrow().value),
+            Some("other")
+        );
+        tree.insert(&mut Node::with_value(9, "other"));
+        assert_eq!(tree.take(&9), Some(9));
+        assert_eq!(tree.take(&9), None);
+
+        // one of the two sevens is still held outside the tree
+        let mut entries = vec![tree.remove_entry(&7), tree.remove_entry(&7)];
+        entries.sort();
+        assert_eq!(entries, vec![Some((7, "other")), Some((7, "seven"))]);
+        assert_eq!(tree.remove_entry(&7), None);
+        assert!(tree.is_empty());
+
+        let mut splay_map = SplayMap::new();
+        splay_map.insert(1, "one");
+        splay_map.insert(2, "two");
+        assert_eq!(splay_map.take(&1), Some(1));
+        assert_eq!(splay_map.remove_entry(&2), Some((2, "two")));
+        assert_eq!(splay_map.remove_entry(&2), None);
+        assert!(splay_map.is_empty());
+    }
+
+    #[test]
+    fn multiset_counts() {
+        let mut multiset: SplayMultiset<u32> = [4, 1, 4, 2, 4].iter().cloned().collect();
+        multiset.insert_many(2, 3);
+        multiset.insert_many(9, 0);
+        assert_eq!(multiset.len(), 8);
+        assert_eq!(multiset.distinct_len(), 3);
+        assert_eq!(multiset.count(&4), 3);
+        assert_eq!(multiset.count(&9), 0);
+        assert_eq!(
+            multiset.iter().collect::<Vec<_>>(),
+            vec![(1, 1), (2, 4), (4, 3)]
+        );
+
+        assert!(multiset.remove_one(&1));
+        assert!(!multiset.remove_one(&1));
+        assert!(!multiset.contains(&1));
+        assert!(multiset.remove_one(&4));
+        assert_eq!(multiset.count(&4), 2);
+        assert_eq!(multiset.remove_all(&2), 4);
+        assert_eq!(multiset.remove_all(&2), 0);
+        assert_eq!(multiset.len(), 2);
+        assert_eq!(multiset.iter().rev().collect::<Vec<_>>(), vec![(4, 2)]);
+    }
+
+    #[test]
+    fn duplicate_policy() {
+        use std::rc::Rc;
+
+        let mut tree = SplayTree::new();
+        let first = Node::with_value(5, "first");
+        assert!(matches!(
+            tree.insert(&mut first.clone()),
+            InsertOutcome::Inserted
+        ));
+        assert!(matches!(
+            tree.insert_with(&mut Node::with_value(3, "three"), DuplicatePolicy::Reject),
+            Ok(InsertOutcome::Inserted)
+        ));
+
+        let outcome = tree.insert_with(&mut Node::with_value(5, "second"), DuplicatePolicy::Reject);
+        assert_eq!(outcome.err(), Some(SplayError::DuplicateKey));
+        let outcome = tree.insert_with(
+            &mut Node::with_value(5, "second"),
+            DuplicatePolicy::KeepFirst,
+        );
+        assert!(matches!(outcome, Ok(InsertOutcome::Kept)));
+        assert_eq!(tree.len(), 2);
+
+        let outcome =
+            tree.insert_with(&mut Node::with_value(5, "second"), DuplicatePolicy::Replace);
+        match outcome {
+            Ok(InsertOutcome::Replaced(old)) => assert!(Rc::ptr_eq(&old, &first)),
+            _ => panic!("replacing did not hand back the old node"),
+        }
+        let values: Vec<_> = tree.iter().map(|node| node.borrow().value).collect();
+        assert_eq!(values, vec!["three", "second"]);
+
+        let outcome = tree.insert_with(&mut Node::with_value(5, "third"), DuplicatePolicy::Allow);
+        assert!(matches!(outcome, Ok(InsertOutcome::Duplicated)));
+        assert_eq!(tree.len(), 3);
+    }
+
+    #[test]
+    fn custom_comparator() {
+        use std::cmp::Ordering;
+        use std::ops::Bound;
+
+        let mut tree = SplayTree::with_comparator(|a: &String, b: &String| {
+            a.to_lowercase().cmp(&b.to_lowercase())
+        });
+        for key in ["b", "A", "c", "B"] {
+            tree.insert(&mut Node::new(key.to_string()));
+        }
+        assert_eq!(tree.len(), 4);
+        assert_eq!(tree.remove(&"C".to_string()), Some("c".to_string()));
+        assert_eq!(tree.rank(&"b".to_string()), 1);
+        let keys: Vec<String> = tree
+            .range("a".to_string().."b".to_string())
+            .map(|node| node.borrow().key.clone())
+            .collect();
+        assert_eq!(keys, vec!["A"]);
+
+        // reverse order through a comparator type
+        #[derive(Clone, Copy)]
+        struct Reverse;
+
+        impl Compare<u32> for Reverse {
+            fn compare(&self, a: &u32, b: &u32) -> Ordering {
+                b.cmp(a)
+            }
+        }
+
+        let mut tree = SplayTree::with_comparator(Reverse);
+        for key in 0..10 {
+            tree.insert(&mut Node::new(key));
+        }
+        tree.delete(4);
+        assert_eq!(
+            tree.keys().collect::<Vec<_>>(),
+            vec![9, 8, 7, 6, 5, 3, 2, 1, 0]
+        );
+        assert_eq!(tree.select(0).map(|node| node.borrow().key), Some(9));
+
+        // ordering by a projected field of keys that are not Ord
+        #[derive(Clone, Debug)]
+        struct Point {
+            x: f64,
+            y: f64,
+        }
+
+        let mut tree = SplayTree::with_comparator(|a: &Point, b: &Point| a.y.total_cmp(&b.y));
+        for (x, y) in [(0.0, 2.5), (1.0, -1.0), (2.0, 0.5)] {
+            tree.insert(&mut Node::new(Point { x, y }));
+        }
+        let xs: Vec<f64> = tree.keys().map(|point| point.x).collect();
+        assert_eq!(xs, vec![1.0, 2.0, 0.0]);
+
+        // augmented tree in reverse order, so the range runs from 7 down to 2
+        let mut tree: SplayTree<i64, i64, SumMax, _> =
+            SplayTree::augmented_with_comparator(|a: &i64, b: &i64| b.cmp(a));
+        for key in 0..10 {
+            tree.insert(&mut Node::with_value(key, key * 10));
+        }
+        assert_eq!(
+            tree.fold_range((Bound::Included(7), Bound::Included(2))),
+            Some(SumMax { sum: 270, max: 70 })
+        );
+        assert_eq!(tree.fold_range(2..=7), None);
+    }
+
+    #[test]
+    fn borrowed_lookups() {
+        let mut tree = SplayTree::new();
+        for key in ["pear", "apple", "fig"] {
+            tree.insert(&mut Node::new(key.to_string()));
+        }
+        assert!(tree.contains("fig"));
+        assert!(!tree.contains("kiwi"));
+        tree.splay_key("pear");
+        assert_eq!(tree.root.as_ref().unwrap().borrow().key, "pear");
+        assert_eq!(
+            tree.get("apple").map(|node| node.borrow().key.clone()),
+            Some("apple".to_string())
+        );
+        assert_eq!(tree.remove("apple"), Some("apple".to_string()));
+        assert_eq!(tree.try_delete("apple"), Err(SplayError::KeyNotFound));
+
+        let mut splay_map = SplayMap::new();
+        splay_map.insert("one".to_string(), 1);
+        splay_map.insert("two".to_string(), 2);
+        assert_eq!(*splay_map.get("one").unwrap(), 1);
+        *splay_map.get_mut("two").unwrap() += 20;
+        assert!(splay_map.contains_key("two"));
+        assert_eq!(splay_map.remove_entry("two"), Some(("two".to_string(), 22)));
+
+        let mut multiset: SplayMultiset<String> =
+            ["a", "b", "a"].iter().map(|key| key.to_string()).collect();
+        assert_eq!(multiset.count("a"), 2);
+        assert!(multiset.remove_one("b"));
+        assert_eq!(multiset.remove_all("a"), 2);
+        assert!(!multiset.contains("a"));
+    }
+
+    #[test]
+    fn lookups_without_splay() {
+        use std::sync::Arc;
+        use std::sync::RwLock;
+        use std::thread;
+
+        let mut tree = SplayTree::new();
+        for key in [5, 3, 8, 1, 4] {
+            tree.insert(&mut Node::new(key));
+        }
+        let root = tree.root.as_ref().unwrap().borrow().key;
+        let shared = &tree;
+        assert!(shared.contains_no_splay(&3));
+        assert!(!shared.contains_no_splay(&7));
+        assert_eq!(shared.get_no_splay(&8).unwrap().borrow().key, 8);
+        assert_eq!(tree.root.as_ref().unwrap().borrow().key, root);
+        tree.get_with(&1, Access::NoSplay);
+        assert_eq!(tree.root.as_ref().unwrap().borrow().key, root);
+        tree.get_with(&1, Access::Splay);
+        assert_eq!(tree.root.as_ref().unwrap().borrow().key, 1);
+
+        let mut splay_map = SplayMap::new();
+        splay_map.insert(2, "two");
+        splay_map.insert(6, "six");
+        assert_eq!(splay_map.get_no_splay(&2), Some("two"));
+        assert!(!splay_map.contains_key_no_splay(&4));
+        assert_eq!(splay_map.get_with(&6, Access::Splay), Some("six"));
+
+        let owned: OwnedSplayTree<i32> = (0..10).collect();
+        assert!(owned.contains_no_splay(&9));
+        assert!(!owned.contains_no_splay(&10));
+
+        // readers only take the shared lock
+        let arena = Arc::new(RwLock::new(ArenaSplayTree::new()));
+        for key in 0..100 {
+            arena.write().unwrap().insert(key, key * 2);
+        }
+        let readers: Vec<_> = (0..4)
+            .map(|_| {
+                let arena = Arc::clone(&arena);
+                thread::spawn(move || {
+                    let arena = arena.read().unwrap();
+                    (0..100).all(|key| arena.get_no_splay(&key) == Some(&(key * 2)))
+                        && !arena.contains_key_no_splay(&100)
+                })
+            })
+            .collect();
+        for reader in readers {
+            assert!(reader.join().unwrap());
+        }
+    }
+
+    #[test]
+    fn concurrent_stress() {
+        use std::collections::BTreeMap;
+        use std::sync::Arc;
+        use std::thread;
+
+        fn shared<T: Send + Sync>(_: &T) {}
+
+        let tree = Arc::new(ConcurrentSplayTree::new());
+        shared(&tree);
+
+        // every thread owns the keys equal to its id modulo 4, so the final
+        // contents can be checked against a map built the same way
+        let workers: Vec<_> = (0..4u64)
+            .map(|id| {
+                let tree = Arc::clone(&tree);
+                thread::spawn(move || {
+                    let mut seed: u64 = 3438 + id;
+                    let mut expected = BTreeMap::new();
+                    for _ in 0..5000 {
+                        next_seed(&mut seed);
+                        let key = (seed >> 33) % 256 * 4 + id;
+                        match seed % 4 {
+                            0 => {
+                                assert_eq!(tree.remove(&key), expected.remove(&key));
+                            }
+                            1 => {
+                                assert_eq!(tree.get(&key), expected.get(&key).cloned());
+                            }
+                            2 => {
+                                let found = tree.get_with(&key, Access::NoSplay);
+                                assert_eq!(found, expected.get(&key).cloned());
+                            }
+                            _ => {
+                                assert_eq!(tree.insert(key, seed), expected.insert(key, seed));
+                            }
+                        }
+                    }
+                    expected
+                })
+            })
+            .collect();
+
+        let mut expected = BTreeMap::new();
+        for worker in workers {
+            expected.extend(worker.join().unwrap());
+        }
+        assert_eq!(tree.len(), expected.len());
+        let tree = Arc::try_unwrap(tree).unwrap().into_inner();
+        let contents: Vec<_> = tree.iter().map(|(&key, &value)| (key, value)).collect();
+        assert_eq!(contents, expected.into_iter().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn splay_policies() {
+        assert!(SplayPolicy::Sampled(0).should_splay(7, 0));
+        assert!(!SplayPolicy::Sampled(3).should_splay(7, 0));
+        assert!(SplayPolicy::DeeperThan(2).should_splay(0, 3));
+
+        // ascending insertions leave a left path 9, 8, ..., 0 under the root
+        let mut tree = ArenaSplayTree::with_policy(SplayPolicy::Sampled(2));
+        for key in 0..10 {
+            tree.insert(key, ());
+        }
+        tree.splay(0);
+        assert_eq!(tree.iter().count(), 10);
+        assert!(tree.contains_key(&5));
+        assert_eq!(tree.find(&0).1, 0);
+        assert_ne!(tree.find(&5).1, 0);
+        tree.splay(5);
+        assert_eq!(tree.find(&5).1, 0);
+
+        tree.set_policy(SplayPolicy::DeeperThan(1));
+        let shallow = (0..10).find(|key| tree.find(key).1 == 1).unwrap();
+        tree.splay(shallow);
+        assert_eq!(tree.find(&shallow).1, 1);
+        let deep = (0..10).max_by_key(|key| tree.find(key).1).unwrap();
+        tree.splay(deep);
+        assert_eq!(tree.find(&deep).1, 0);
+
+        let concurrent = ConcurrentSplayTree::with_policy(SplayPolicy::Sampled(4));
+        for key in 0..100 {
+            concurrent.insert(key, key + 1);
+        }
+        for key in 0..100 {
+            assert_eq!(concurrent.get(&key), Some(key + 1));
+        }
+        assert!(!concurrent.contains_key(&100));
+        assert!(concurrent.update(&7, |value| *value *= 2));
+        assert!(!concurrent.update(&100, |_| ()));
+        assert_eq!(concurrent.get(&7), Some(16));
+        assert_eq!(concurrent.into_inner().policy(), SplayPolicy::Sampled(4));
+
+        // the wrapped tree's policy decides, updates included
+        let shallow = (0..10).find(|key| tree.find(key).1 == 1).unwrap();
+        let deep = (0..10).max_by_key(|key| tree.find(key).1).unwrap();
+        let concurrent = ConcurrentSplayTree::from(tree);
+        assert_eq!(concurrent.policy(), SplayPolicy::DeeperThan(1));
+        assert!(concurrent.update(&shallow, |value| *value = ()));
+        let tree = concurrent.into_inner();
+        assert_eq!(tree.find(&shallow).1, 1);
+        let concurrent = ConcurrentSplayTree::from(tree);
+        assert!(concurrent.update(&deep, |value| *value = ()));
+        assert_eq!(concurrent.into_inner().find(&deep).1, 0);
+    }
+
+    #[test]
+    fn persistent_snapshots() {
+        use std::collections::BTreeMap;
+
+        // keep every version with the map it should match
+        let mut tree = PersistentSplayTree::new();
+        let mut expected = BTreeMap::new();
+        let mut versions = vec![(tree.snapshot(), expected.clone())];
+        let mut seed: u64 = 3438;
+        for _ in 0..2000 {
+            next_seed(&mut seed);
+            let key = (seed >> 33) % 200;
+            match seed % 3 {
+                0 => assert_eq!(tree.remove(&key), expected.remove(&key)),
+                1 => assert_eq!(tree.get(&key), expected.get(&key)),
+                _ => assert_eq!(tree.insert(key, seed), expected.insert(key, seed)),
+            }
+            assert_eq!(tree.len(), expected.len());
+            if seed.is_multiple_of(16) {
+                versions.push((tree.snapshot(), expected.clone()));
+            }
+        }
+
+        for (version, expected) in &mut versions {
+            let contents: Vec<_> = version.iter().map(|(&key, &value)| (key, value)).collect();
+            assert_eq!(contents, expected.clone().into_iter().collect::<Vec<_>>());
+            // splaying an old version leaves the other versions alone
+            if let Some(&key) = expected.keys().next() {
+                assert_eq!(version.get(&key), expected.get(&key));
+            }
+        }
+        let snapshot = tree.snapshot();
+        assert!(snapshot.ptr_eq(&tree));
+        tree.insert(1000, 0);
+        assert!(!snapshot.ptr_eq(&tree));
+        assert_eq!(snapshot.get_no_splay(&1000), None);
+        for (version, expected) in &versions {
+            assert_eq!(version.len(), expected.len());
+            assert!(version.iter().map(|(key, _)| key).eq(expected.keys()));
+        }
+    }
+
+    #[test]
+    fn deep_clone_and_shallow_share() {
+        use std::rc::Rc;
+
+        // ascending insertions leave one long left path
+        let mut tree = SplayTree::new();
+        for key in 0..1000 {
+            tree.insert(&mut Node::with_value(key, key * 2));
+        }
+        let mut copy = tree.clone();
+        assert!(!Rc::ptr_eq(
+            tree.root.as_ref().unwrap(),
+            copy.root.as_ref().unwrap()
+        ));
+        copy.delete(999);
+        copy.splay(0);
+        assert_eq!(copy.len(), 999);
+        assert_eq!(tree.len(), 1000);
+        assert_eq!(tree.root.as_ref().unwrap().borrow().key, 999);
+        assert!(tree.iter().map(|node| node.borrow().key).eq(0..1000));
+        assert!(copy
+            .iter()
+            .map(|node| node.borrow().value - node.borrow().key)
+            .eq(0..999));
+
+        let node = tree.root.as_ref().unwrap().borrow().clone();
+        assert_eq!(node.size, 1000);
+        assert!(!Rc::ptr_eq(
+            node.left.as_ref().unwrap(),
+            tree.root.as_ref().unwrap().borrow().left.as_ref().unwrap()
+        ));
+
+        let shared = tree.shallow_share();
+        assert!(Rc::ptr_eq(
+            tree.root.as_ref().unwrap(),
+            shared.root.as_ref().unwrap()
+        ));
+        tree.get_no_splay(&500).unwrap().borrow_mut().value = 0;
+        assert_eq!(shared.get_no_splay(&500).unwrap().borrow().value, 0);
+    }
+
+    #[test]
+    fn million_node_chains() {
+        use std::io;
+        use std::io::Write;
+
+        const COUNT: i64 = 1_000_000;
+
+        // ascending insertions leave one left path of every node
+        let mut tree = SplayTree::new();
+        for key in 0..COUNT {
+            tree.insert(&mut Node::new(key));
+        }
+        let root = tree.root.clone().unwrap();
+        assert_eq!(root.borrow().left_most_key(), 0);
+        assert_eq!(root.borrow().right_most_key(), COUNT - 1);
+
+        // both walk the whole path down without splaying
+        assert!(!root.borrow_mut().bstinsert(&mut Node::new(-1)));
+        root.borrow_mut().insert_left_most(Some(Node::new(-2)));
+        assert_eq!(root.borrow().size, COUNT as usize + 2);
+        assert_eq!(root.borrow().left_most_key(), -2);
+        drop(root);
+        let copy = tree.clone();
+        assert!(*tree.root.as_ref().unwrap().borrow() == *copy.root.as_ref().unwrap().borrow());
+        drop(tree);
+        assert_eq!(copy.len(), COUNT as usize + 2);
+        writeln!(io::sink(), "{:?}", copy).unwrap();
+        drop(copy);
+
+        // descending insertions leave one right path
+        let mut tree = SplayTree::new();
+        for key in (0..COUNT).rev() {
+            tree.insert(&mut Node::new(key));
+        }
+        let root = tree.root.clone().unwrap();
+        root.borrow_mut().insert_right_most(Some(Node::new(COUNT)));
+        assert_eq!(root.borrow().right_most_key(), COUNT);
+        assert_eq!(tree.len(), COUNT as usize + 1);
+        drop(root);
+
+        // folds isolate the range by splaying, nothing walks the path
+        assert_eq!(tree.fold_range(..), Some(()));
+        assert_eq!(tree.fold_range(..=COUNT / 2), Some(()));
+        assert_eq!(tree.fold_range(COUNT + 1..), None);
+    }
+
+    #[test]
+    fn splay_sizes_on_long_paths() {
+        let mut tree = SplayTree::new();
+        for key in 0..10_000 {
+            tree.insert(&mut Node::new(key));
+        }
+        for key in [0, 9999, 5000, 1, 9998] {
+            tree.splay(key);
+            assert_eq!(tree.root.as_ref().unwrap().borrow().key, key);
+            for node in tree.iter() {
+                let node = node.borrow();
+                assert_eq!(node.size, node.left_size() + node.right_size() + 1);
+            }
+        }
+        assert!(tree.iter().map(|node| node.borrow().key).eq(0..10_000));
+    }
+
+    #[test]
+    fn bottom_up_splay_and_handles() {
+        use std::collections::BTreeMap;
+
+        // switching strategies on the way checks that both keep the parent
+        // links the bottom-up splay walks
+        let mut tree = ArenaSplayTree::with_strategy(SplayStrategy::BottomUp);
+        let mut expected = BTreeMap::new();
+        let mut seed: u64 = 3438;
+        for round in 0..20_000 {
+            if round % 5000 == 0 {
+                let strategy = match tree.strategy() {
+                    SplayStrategy::TopDown => SplayStrategy::BottomUp,
+                    SplayStrategy::BottomUp => SplayStrategy::TopDown,
+                };
+                tree.set_strategy(strategy);
+            }
+            next_seed(&mut seed);
+            let key = (seed >> 33) % 1000;
+            match seed % 4 {
+                0 => assert_eq!(tree.remove(&key), expected.remove(&key)),
+                1 => assert_eq!(tree.get(&key), expected.get(&key)),
+                2 => {
+                    let handle = tree.handle(&key);
+                    assert_eq!(handle.is_some(), expected.contains_key(&key));
+                    if let Some(handle) = handle {
+                        assert!(tree.splay_handle(handle));
+                        assert_eq!(tree.get_handle(handle), Some((&key, &expected[&key])));
+                        assert_eq!(tree.find(&key).1, 0);
+                    }
+                }
+                _ => assert_eq!(tree.insert(key, seed), expected.insert(key, seed)),
+            }
+        }
+        assert!(tree.iter().map(|(key, _)| key).eq(expected.keys()));
+
+        let first = *expected.keys().next().unwrap();
+        let handle = tree.handle(&first).unwrap();
+        tree.remove(&first);
+        assert_eq!(tree.get_handle(handle), None);
+        assert!(!tree.splay_handle(handle));
+
+        // the reinserted node takes the freed slot, old handles stay stale
+        let mut tree = ArenaSplayTree::new();
+        tree.insert(1, "one");
+        let stale = tree.handle(&1).unwrap();
+        tree.remove(&1);
+        tree.insert(7, "seven");
+        assert_eq!(tree.get_handle(stale), None);
+        assert!(!tree.splay_handle(stale));
+        let fresh = tree.handle(&7).unwrap();
+        assert_ne!(fresh, stale);
+        assert_eq!(tree.get_handle(fresh), Some((&7, &"seven")));
+        tree.clear();
+        tree.insert(7, "seven");
+        assert_eq!(tree.get_handle(fresh), None);
+
+        // SplayTree keeps the search path instead of parent links
+        let mut splay_tree = SplayTree::new();
+        splay_tree.set_strategy(SplayStrategy::BottomUp);
+        let mut expected = BTreeMap::new();
+        for round in 0..20_000 {
+            if round % 5000 == 0 {
+                let strategy = match splay_tree.strategy() {
+                    SplayStrategy::TopDown => SplayStrategy::BottomUp,
+                    SplayStrategy::BottomUp => SplayStrategy::TopDown,
+                };
+                splay_tree.set_strategy(strategy);
+            }
+            next_seed(&mut seed);
+            let key = (seed >> 33) % 1000;
+            match seed % 3 {
+                0 => assert_eq!(splay_tree.remove(&key), expected.remove(&key).map(|_| key)),
+                1 => {
+                    let node = splay_tree.get(&key);
+                    assert_eq!(
+                        node.map(|node| node.borrow().value),
+                        expected.get(&key).copied()
+                    );
+                }
+                _ => {
+                    if !splay_tree.contains(&key) {
+                        splay_tree.insert(&mut Node::with_value(key, seed));
+                        expected.insert(key, seed);
+                    }
+                }
+            }
+            assert_eq!(check_sizes(&splay_tree.root), expected.len());
+        }
+        assert!(splay_tree.keys().eq(expected.keys().copied()));
+
+        // summaries follow the rotations, the ascending insertions leave 0 at
+        // the bottom of one long path
+        let mut splay_tree: SplayTree<i64, i64, SumMax> = SplayTree::default();
+        for key in 0..100 {
+            splay_tree.insert(&mut Node::with_value(key, key));
+        }
+        splay_tree.set_strategy(SplayStrategy::BottomUp);
+        assert_eq!(splay_tree.get(&0).map(|node| node.borrow().key), Some(0));
+        assert_eq!(
+            splay_tree.fold_range(10..20),
+            Some(SumMax { sum: 145, max: 19 })
+        );
+        assert_eq!(splay_tree.fold_range(..).map(|s| s.sum), Some(4950));
+    }
 }
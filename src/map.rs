@@ -0,0 +1,383 @@
+use crate::iter;
+use crate::Access;
+use crate::Entry;
+use crate::Node;
+use crate::SplayTree;
+
+use std::borrow;
+use std::cell::Ref;
+use std::cell::RefCell;
+use std::cell::RefMut;
+use std::fmt::Debug;
+use std::marker::PhantomData;
+use std::mem;
+use std::ops::RangeBounds;
+use std::rc::Rc;
+
+#[derive(Debug)]
+pub struct SplayMap<K, V> {
+    tree: SplayTree<K, V>,
+}
+
+impl<K, V> Default for SplayMap<K, V> {
+    fn default() -> Self {
+        SplayMap {
+            tree: SplayTree::default(),
+        }
+    }
+}
+
+impl<K: Ord + Clone + Debug, V> SplayMap<K, V> {
+    pub fn new() -> Self {
+        SplayMap {
+            tree: SplayTree::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.tree.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tree.is_empty()
+    }
+
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter {
+            inner: self.tree.iter(),
+        }
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_, K, V> {
+        IterMut {
+            inner: self.tree.iter(),
+        }
+    }
+
+    pub fn range<R: RangeBounds<K>>(&mut self, range: R) -> Range<'_, K, V> {
+        Range {
+            inner: self.tree.range(range),
+        }
+    }
+
+    pub fn range_mut<R: RangeBounds<K>>(&mut self, range: R) -> RangeMut<'_, K, V> {
+        RangeMut {
+            inner: self.tree.range(range),
+        }
+    }
+
+    pub fn keys(&self) -> iter::Keys<'_, K, V> {
+        self.tree.keys()
+    }
+
+    pub fn values(&self) -> Values<'_, K, V> {
+        Values {
+            inner: self.tree.iter(),
+        }
+    }
+
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        if self.tree.splay_to_root(&key) {
+            let mut root = self.tree.root.as_ref().unwrap().borrow_mut();
+            return Some(mem::replace(&mut root.value, value));
+        }
+
+        self.tree.insert_root(Node::with_value(key, value));
+        None
+    }
+
+    pub fn entry(&mut self, key: K) -> Entry<'_, K, V> {
+        self.tree.entry(key)
+    }
+
+    pub fn get<Q: ?Sized + Ord>(&mut self, key: &Q) -> Option<Ref<'_, V>>
+    where
+        K: borrow::Borrow<Q>,
+    {
+        if !self.tree.splay_to_root(key) {
+            return None;
+        }
+        let root = self.tree.root.as_ref().unwrap();
+        Some(Ref::map(root.borrow(), |node| &node.value))
+    }
+
+    pub fn get_mut<Q: ?Sized + Ord>(&mut self, key: &Q) -> Option<RefMut<'_, V>>
+    where
+        K: borrow::Borrow<Q>,
+    {
+        if !self.tree.splay_to_root(key) {
+            return None;
+        }
+        let root = self.tree.root.as_ref().unwrap();
+        Some(RefMut::map(root.borrow_mut(), |node| &mut node.value))
+    }
+
+    // splay or not depending on access, the value is cloned like get_no_splay
+    pub fn get_with<Q: ?Sized + Ord>(&mut self, key: &Q, access: Access) -> Option<V>
+    where
+        K: borrow::Borrow<Q>,
+        V: Clone,
+    {
+        let node = self.tree.get_with(key, access)?;
+        let value = node.borrow().value.clone();
+        Some(value)
+    }
+
+    // lookup through a shared reference, the node is only reached through a
+    // temporary handle so the value is cloned out of it
+    pub fn get_no_splay<Q: ?Sized + Ord>(&self, key: &Q) -> Option<V>
+    where
+        K: borrow::Borrow<Q>,
+        V: Clone,
+    {
+        let node = self.tree.get_no_splay(key)?;
+        let value = node.borrow().value.clone();
+        Some(value)
+    }
+
+    pub fn contains_key_no_splay<Q: ?Sized + Ord>(&self, key: &Q) -> bool
+    where
+        K: borrow::Borrow<Q>,
+    {
+        self.tree.contains_no_splay(key)
+    }
+
+    pub fn contains_key<Q: ?Sized + Ord>(&mut self, key: &Q) -> bool
+    where
+        K: borrow::Borrow<Q>,
+    {
+        self.tree.splay_to_root(key)
+    }
+
+    pub fn split_off(&mut self, key: &K) -> Self {
+        SplayMap {
+            tree: self.tree.split_off(key),
+        }
+    }
+
+    pub fn append(&mut self, other: &mut Self) {
+        let disjoint = match (&self.tree.root, &other.tree.root) {
+            (Some(left), Some(right)) => {
+                left.borrow().right_most_key() < right.borrow().left_most_key()
+                    || right.borrow().right_most_key() < left.borrow().left_most_key()
+            }
+            _ => true,
+        };
+        if disjoint {
+            self.tree.append(&mut other.tree);
+            return;
+        }
+
+        // overlapping keys take the value from other
+        while let Some(node) = other.tree.pop_left_most() {
+            match Rc::try_unwrap(node) {
+                Ok(node) => {
+                    let node = node.into_inner();
+                    self.insert(node.key, node.value);
+                }
+                Err(_) => unreachable!("map node is still shared"),
+            }
+        }
+    }
+
+    pub fn remove<Q: ?Sized + Ord>(&mut self, key: &Q) -> Option<V>
+    where
+        K: borrow::Borrow<Q>,
+    {
+        self.remove_entry(key).map(|(_, value)| value)
+    }
+
+    // remove key, return the key that was stored
+    pub fn take<Q: ?Sized + Ord>(&mut self, key: &Q) -> Option<K>
+    where
+        K: borrow::Borrow<Q>,
+    {
+        self.remove_entry(key).map(|(key, _)| key)
+    }
+
+    pub fn remove_entry<Q: ?Sized + Ord>(&mut self, key: &Q) -> Option<(K, V)>
+    where
+        K: borrow::Borrow<Q>,
+    {
+        let removed = self.tree.take_node(key)?;
+
+        // nodes of the map only leave it tied to a borrow of the map, so the
+        // removed node is unique here
+        match Rc::try_unwrap(removed) {
+            Ok(node) => {
+                let node = node.into_inner();
+                Some((node.key, node.value))
+            }
+            Err(_) => unreachable!("map node is still shared"),
+        }
+    }
+}
+
+pub struct Iter<'a, K, V> {
+    inner: iter::Iter<'a, K, V>,
+}
+
+pub struct Range<'a, K, V> {
+    inner: iter::Range<'a, K, V>,
+}
+
+pub struct RangeMut<'a, K, V> {
+    inner: iter::Range<'a, K, V>,
+}
+
+pub struct Values<'a, K, V> {
+    inner: iter::Iter<'a, K, V>,
+}
+
+pub struct IterMut<'a, K, V> {
+    inner: iter::Iter<'a, K, V>,
+}
+
+// read handle to one entry of the map, the key and value are borrowed out
+// of the node instead of cloned
+pub struct ValueRef<'a, K, V> {
+    node: Rc<RefCell<Node<K, V>>>,
+    marker: PhantomData<&'a V>,
+}
+
+impl<'a, K, V> ValueRef<'a, K, V> {
+    fn new(node: Rc<RefCell<Node<K, V>>>) -> Self {
+        ValueRef {
+            node,
+            marker: PhantomData,
+        }
+    }
+
+    pub fn key(&self) -> Ref<'_, K> {
+        Ref::map(self.node.borrow(), |node| &node.key)
+    }
+
+    pub fn get(&self) -> Ref<'_, V> {
+        Ref::map(self.node.borrow(), |node| &node.value)
+    }
+}
+
+// handle to one value of the map, it lives no longer than the borrow of the
+// map that produced it
+pub struct ValueMut<'a, K, V> {
+    node: Rc<RefCell<Node<K, V>>>,
+    marker: PhantomData<&'a mut V>,
+}
+
+impl<'a, K, V> ValueMut<'a, K, V> {
+    pub fn key(&self) -> Ref<'_, K> {
+        Ref::map(self.node.borrow(), |node| &node.key)
+    }
+
+    pub fn get(&self) -> Ref<'_, V> {
+        Ref::map(self.node.borrow(), |node| &node.value)
+    }
+
+    pub fn get_mut(&mut self) -> RefMut<'_, V> {
+        RefMut::map(self.node.borrow_mut(), |node| &mut node.value)
+    }
+
+    pub fn set(&mut self, value: V) -> V {
+        mem::replace(&mut self.get_mut(), value)
+    }
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+    type Item = ValueRef<'a, K, V>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(ValueRef::new)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, K, V> DoubleEndedIterator for Iter<'a, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back().map(ValueRef::new)
+    }
+}
+
+impl<'a, K, V> ExactSizeIterator for Iter<'a, K, V> {}
+
+impl<'a, K, V> Iterator for Values<'a, K, V> {
+    type Item = ValueRef<'a, K, V>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(ValueRef::new)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, K, V> DoubleEndedIterator for Values<'a, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back().map(ValueRef::new)
+    }
+}
+
+impl<'a, K, V> ExactSizeIterator for Values<'a, K, V> {}
+
+impl<'a, K, V> Iterator for IterMut<'a, K, V> {
+    type Item = ValueMut<'a, K, V>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|node| ValueMut {
+            node,
+            marker: PhantomData,
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, K, V> DoubleEndedIterator for IterMut<'a, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back().map(|node| ValueMut {
+            node,
+            marker: PhantomData,
+        })
+    }
+}
+
+impl<'a, K, V> ExactSizeIterator for IterMut<'a, K, V> {}
+
+impl<'a, K: Ord, V> Iterator for Range<'a, K, V> {
+    type Item = ValueRef<'a, K, V>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(ValueRef::new)
+    }
+}
+
+impl<'a, K: Ord, V> DoubleEndedIterator for Range<'a, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back().map(ValueRef::new)
+    }
+}
+
+impl<'a, K: Ord, V> Iterator for RangeMut<'a, K, V> {
+    type Item = ValueMut<'a, K, V>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|node| ValueMut {
+            node,
+            marker: PhantomData,
+        })
+    }
+}
+
+impl<'a, K: Ord, V> DoubleEndedIterator for RangeMut<'a, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back().map(|node| ValueMut {
+            node,
+            marker: PhantomData,
+        })
+    }
+}
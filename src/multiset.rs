@@ -0,0 +1,143 @@
+use crate::map;
+use crate::SplayMap;
+
+use std::borrow::Borrow;
+use std::fmt::Debug;
+use std::iter::FromIterator;
+
+// duplicates are counted on one node instead of being stored as separate
+// nodes, so every key is splayed and removed as a whole
+#[derive(Debug)]
+pub struct SplayMultiset<K> {
+    map: SplayMap<K, usize>,
+    // number of keys counted with their multiplicity
+    len: usize,
+}
+
+impl<K> Default for SplayMultiset<K> {
+    fn default() -> Self {
+        SplayMultiset {
+            map: SplayMap::default(),
+            len: 0,
+        }
+    }
+}
+
+impl<K: Ord + Clone + Debug> SplayMultiset<K> {
+    pub fn new() -> Self {
+        SplayMultiset::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    // number of different keys
+    pub fn distinct_len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn insert(&mut self, key: K) {
+        self.insert_many(key, 1);
+    }
+
+    pub fn insert_many(&mut self, key: K, count: usize) {
+        if count == 0 {
+            return;
+        }
+        *self.map.entry(key).or_insert(0) += count;
+        self.len += count;
+    }
+
+    pub fn count<Q: ?Sized + Ord>(&mut self, key: &Q) -> usize
+    where
+        K: Borrow<Q>,
+    {
+        self.map.get(key).map_or(0, |count| *count)
+    }
+
+    pub fn contains<Q: ?Sized + Ord>(&mut self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+    {
+        self.map.contains_key(key)
+    }
+
+    // remove one copy of key, return false if there was none
+    pub fn remove_one<Q: ?Sized + Ord>(&mut self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+    {
+        let last = match self.map.get_mut(key) {
+            Some(mut count) => {
+                *count -= 1;
+                *count == 0
+            }
+            None => return false,
+        };
+        if last {
+            self.map.remove(key);
+        }
+        self.len -= 1;
+        true
+    }
+
+    // remove every copy of key, return how many there were
+    pub fn remove_all<Q: ?Sized + Ord>(&mut self, key: &Q) -> usize
+    where
+        K: Borrow<Q>,
+    {
+        let count = self.map.remove(key).unwrap_or(0);
+        self.len -= count;
+        count
+    }
+
+    // every key once, in order, with its multiplicity
+    pub fn iter(&self) -> Iter<'_, K> {
+        Iter {
+            inner: self.map.iter(),
+        }
+    }
+}
+
+impl<K: Ord + Clone + Debug> FromIterator<K> for SplayMultiset<K> {
+    fn from_iter<I: IntoIterator<Item = K>>(iter: I) -> Self {
+        let mut multiset = SplayMultiset::new();
+        for key in iter {
+            multiset.insert(key);
+        }
+        multiset
+    }
+}
+
+pub struct Iter<'a, K> {
+    inner: map::Iter<'a, K, usize>,
+}
+
+impl<'a, K: Clone> Iterator for Iter<'a, K> {
+    type Item = (K, usize);
+
+    fn next(&mut self) -> Option<(K, usize)> {
+        self.inner
+            .next()
+            .map(|entry| (entry.key().clone(), *entry.get()))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, K: Clone> DoubleEndedIterator for Iter<'a, K> {
+    fn next_back(&mut self) -> Option<(K, usize)> {
+        self.inner
+            .next_back()
+            .map(|entry| (entry.key().clone(), *entry.get()))
+    }
+}
+
+impl<'a, K: Clone> ExactSizeIterator for Iter<'a, K> {}
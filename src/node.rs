@@ -1,113 +1,323 @@
+use crate::augment::sealed::Token;
+use crate::Augment;
+use crate::Compare;
+use crate::Natural;
+
 use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::fmt;
 use std::fmt::Debug;
 use std::mem;
 use std::rc::Rc;
 
-#[derive(Clone, Debug)]
-pub struct Node<K> {
-    pub left: SplayNode<K>,
-    pub right: SplayNode<K>,
+pub struct Node<K, V = (), A = ()> {
+    pub left: SplayNode<K, V, A>,
+    pub right: SplayNode<K, V, A>,
     pub key: K,
+    pub value: V,
+    // number of nodes in the subtree rooted here, including itself
+    pub size: usize,
+    // augmented value of the subtree rooted here
+    pub summary: A,
+}
+
+pub type SplayNode<K, V = (), A = ()> = Option<Rc<RefCell<Node<K, V, A>>>>;
+
+// children are shown by their keys only, a derived impl would print the
+// whole subtree with one stack frame per level
+impl<K: Debug, V: Debug, A: Debug> Debug for Node<K, V, A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Node")
+            .field("left", &ChildKey(&self.left))
+            .field("right", &ChildKey(&self.right))
+            .field("key", &self.key)
+            .field("value", &self.value)
+            .field("size", &self.size)
+            .field("summary", &self.summary)
+            .finish()
+    }
 }
 
-pub type SplayNode<K> = Option<Rc<RefCell<Node<K>>>>;
+struct ChildKey<'a, K, V, A>(&'a SplayNode<K, V, A>);
+
+impl<'a, K: Debug, V, A> Debug for ChildKey<'a, K, V, A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            None => f.write_str("None"),
+            Some(child) => match child.try_borrow() {
+                Ok(child) => f.debug_tuple("Some").field(&child.key).finish(),
+                Err(_) => f.write_str("Some(<borrowed>)"),
+            },
+        }
+    }
+}
 
-impl<K: Ord> PartialEq for Node<K> {
+// equal keys and values in the same shape. the pairs of subtrees still to
+// compare are kept on a stack instead of recursing
+impl<K: Ord, V: PartialEq, A> PartialEq for Node<K, V, A> {
     fn eq(&self, other: &Self) -> bool {
-        self.key == other.key && self.left == other.left && self.right == other.right
+        let mut stack = Vec::new();
+        if !self.eq_alone(other, &mut stack) {
+            return false;
+        }
+        while let Some((left, right)) = stack.pop() {
+            if !left.borrow().eq_alone(&right.borrow(), &mut stack) {
+                return false;
+            }
+        }
+        true
     }
-    fn ne(&self, other: &Self) -> bool {
-        !self.eq(other)
+}
+
+type EqPair<K, V, A> = (Rc<RefCell<Node<K, V, A>>>, Rc<RefCell<Node<K, V, A>>>);
+
+impl<K: Ord, V: PartialEq, A> Node<K, V, A> {
+    // compare key and value and whether the same children are there, the
+    // pairs of children are left on the stack
+    fn eq_alone(&self, other: &Self, stack: &mut Vec<EqPair<K, V, A>>) -> bool {
+        if self.key != other.key || self.value != other.value {
+            return false;
+        }
+        for (mine, theirs) in [(&self.left, &other.left), (&self.right, &other.right)] {
+            match (mine, theirs) {
+                (None, None) => {}
+                (Some(mine), Some(theirs)) => stack.push((mine.clone(), theirs.clone())),
+                _ => return false,
+            }
+        }
+        true
+    }
+}
+
+// a clone owns a copy of the whole subtree, so splaying either of them can't
+// change the other. the copy is made with a stack instead of recursion
+impl<K: Clone, V: Clone, A: Clone> Clone for Node<K, V, A> {
+    fn clone(&self) -> Self {
+        let mut stack = Vec::new();
+        let mut root = self.clone_alone();
+        root.left = Node::clone_link(&self.left, &mut stack);
+        root.right = Node::clone_link(&self.right, &mut stack);
+        while let Some((original, copy)) = stack.pop() {
+            let original = original.borrow();
+            let mut copy = copy.borrow_mut();
+            copy.left = Node::clone_link(&original.left, &mut stack);
+            copy.right = Node::clone_link(&original.right, &mut stack);
+        }
+        root
+    }
+}
+
+type ClonePair<K, V, A> = (Rc<RefCell<Node<K, V, A>>>, Rc<RefCell<Node<K, V, A>>>);
+
+impl<K: Clone, V: Clone, A: Clone> Node<K, V, A> {
+    // copy of the node without its children
+    fn clone_alone(&self) -> Self {
+        Node {
+            left: None,
+            right: None,
+            key: self.key.clone(),
+            value: self.value.clone(),
+            size: self.size,
+            summary: self.summary.clone(),
+        }
+    }
+
+    // the children of the copy are filled in when its pair leaves the stack
+    fn clone_link(
+        link: &SplayNode<K, V, A>,
+        stack: &mut Vec<ClonePair<K, V, A>>,
+    ) -> SplayNode<K, V, A> {
+        let original = link.as_ref()?;
+        let copy = Rc::new(RefCell::new(original.borrow().clone_alone()));
+        stack.push((original.clone(), copy.clone()));
+        Some(copy)
     }
 }
 
-impl<K: Ord + Clone + Debug> Node<K> {
+impl<K, V, A> Node<K, V, A> {
+    pub fn left_size(&self) -> usize {
+        self.left.as_ref().map_or(0, |left| left.borrow().size)
+    }
+
+    pub fn right_size(&self) -> usize {
+        self.right.as_ref().map_or(0, |right| right.borrow().size)
+    }
+}
+
+impl<K, V, A: Augment<K, V>> Node<K, V, A> {
+    // hand the lazy tags of this node to its children
+    pub(crate) fn push_down(&mut self) {
+        A::push_down(self, Token);
+    }
+
+    // recompute the subtree size and summary from the children, which have to
+    // be up to date
+    pub fn update(&mut self) {
+        self.size = self.left_size() + self.right_size() + 1;
+
+        let left = self.left.as_ref().map(|left| left.borrow());
+        let right = self.right.as_ref().map(|right| right.borrow());
+        let summary = A::combine(
+            left.as_ref().map(|left| &left.summary),
+            &self.key,
+            &self.value,
+            right.as_ref().map(|right| &right.summary),
+        );
+        drop(left);
+        drop(right);
+        self.summary = summary;
+    }
+}
+
+impl<K: Clone + Debug> Node<K> {
     pub fn new(k: K) -> Rc<RefCell<Self>> {
+        Node::with_value(k, ())
+    }
+}
+
+impl<K: Clone + Debug, V, A: Augment<K, V>> Node<K, V, A> {
+    pub fn with_value(k: K, v: V) -> Rc<RefCell<Self>> {
+        let summary = A::combine(None, &k, &v, None);
         Rc::new(RefCell::new(Node {
             left: None,
             right: None,
             key: k,
+            value: v,
+            size: 1,
+            summary,
         }))
     }
 
-    pub fn insert_left_most(&mut self, inserted: SplayNode<K>) {
-        if inserted.is_none() {
-            return;
-        }
-        let mut temp = self;
+    pub fn insert_left_most(&mut self, inserted: SplayNode<K, V, A>) {
+        self.insert_at_end(inserted, |node| &mut node.left);
+    }
 
-        if let Some(ref mut current) = temp.left {
-            if current.borrow().left.is_none() {
-                current.borrow_mut().left = inserted;
-            } else {
-                current.borrow_mut().insert_left_most(inserted);
-            }
-        } else {
-            temp.left = inserted;
-        }
+    pub fn insert_right_most(&mut self, inserted: SplayNode<K, V, A>) {
+        self.insert_at_end(inserted, |node| &mut node.right);
     }
 
-    pub fn insert_right_most(&mut self, inserted: SplayNode<K>) {
+    // hang inserted at the end of the path that always takes the side child,
+    // then update the nodes on that path from the bottom up
+    fn insert_at_end(
+        &mut self,
+        inserted: SplayNode<K, V, A>,
+        side: fn(&mut Self) -> &mut SplayNode<K, V, A>,
+    ) {
         if inserted.is_none() {
             return;
         }
-        let mut temp = self;
 
-        if let Some(ref mut current) = temp.right {
-            if current.borrow().right.is_none() {
-                current.borrow_mut().right = inserted;
-            } else {
-                current.borrow_mut().insert_right_most(inserted);
-            }
-        } else {
-            temp.right = inserted;
+        let mut path = Vec::new();
+        let mut next = side(self).clone();
+        while let Some(node) = next {
+            next = side(&mut node.borrow_mut()).clone();
+            path.push(node);
+        }
+        match path.last() {
+            Some(last) => *side(&mut last.borrow_mut()) = inserted,
+            None => *side(self) = inserted,
         }
+        while let Some(node) = path.pop() {
+            node.borrow_mut().update();
+        }
+        self.update();
     }
 
     pub fn left_most_key(&self) -> K {
-        if let Some(ref left) = self.left {
-            return left.borrow().left_most_key();
-        } else {
-            return self.key.clone();
+        let mut current = match self.left {
+            Some(ref left) => left.clone(),
+            None => return self.key.clone(),
+        };
+        loop {
+            let next = current.borrow().left.clone();
+            match next {
+                Some(next) => current = next,
+                None => return current.borrow().key.clone(),
+            }
         }
     }
 
     pub fn right_most_key(&self) -> K {
-        if let Some(ref right) = self.right {
-            return right.borrow().right_most_key();
-        } else {
-            return self.key.clone();
+        let mut current = match self.right {
+            Some(ref right) => right.clone(),
+            None => return self.key.clone(),
+        };
+        loop {
+            let next = current.borrow().right.clone();
+            match next {
+                Some(next) => current = next,
+                None => return current.borrow().key.clone(),
+            }
         }
     }
 
-    pub fn bstinsert(&mut self, inserted: &mut Rc<RefCell<Node<K>>>) {
+    // insert below self as in a plain binary search tree, equal keys go to
+    // the left. return true if an equal key was met on the way
+    pub fn bstinsert_by<C: Compare<K>>(
+        &mut self,
+        inserted: &mut Rc<RefCell<Node<K, V, A>>>,
+        cmp: &C,
+    ) -> bool {
         let key = inserted.borrow().key.clone();
-        if self.key == key {
-            let mut temp = mem::take(&mut self.left);
-
-            // new node inserted to self's left
-            self.left = Some(inserted.clone());
-
-            // update nodes
-            self.left.as_mut().unwrap().borrow_mut().left = temp;
-        } else if self.key > key {
-            if self.left.is_some() {
-                self.left.as_mut().unwrap().borrow_mut().bstinsert(inserted);
-            } else {
-                // insert node as left child
+        let mut duplicated = false;
+
+        // NOTE: walk down without recursion and keep the path, the sizes and
+        // summaries on it are updated from the bottom up once the node is in
+        let mut path = Vec::new();
+        let mut next = self.bststep(inserted, &key, cmp, &mut duplicated);
+        while let Some(node) = next {
+            next = node
+                .borrow_mut()
+                .bststep(inserted, &key, cmp, &mut duplicated);
+            path.push(node);
+        }
+        while let Some(node) = path.pop() {
+            node.borrow_mut().update();
+        }
+        self.update();
+        duplicated
+    }
+
+    // child to go on with, or None once inserted has been linked below self
+    fn bststep<C: Compare<K>>(
+        &mut self,
+        inserted: &Rc<RefCell<Node<K, V, A>>>,
+        key: &K,
+        cmp: &C,
+        duplicated: &mut bool,
+    ) -> SplayNode<K, V, A> {
+        match cmp.compare(&self.key, key) {
+            Ordering::Equal => {
+                // new node inserted to self's left, on top of the old left
+                let temp = mem::take(&mut self.left);
+                inserted.borrow_mut().left = temp;
+                inserted.borrow_mut().update();
                 self.left = Some(inserted.clone());
+                *duplicated = true;
+                None
             }
-        } else if self.key < key {
-            if self.right.is_some() {
-                self.right
-                    .as_mut()
-                    .unwrap()
-                    .borrow_mut()
-                    .bstinsert(inserted);
-            } else {
-                // insert node as right child
-                self.right = Some(inserted.clone());
-            }
+            Ordering::Greater => match self.left {
+                Some(ref left) => Some(left.clone()),
+                None => {
+                    // insert node as left child
+                    self.left = Some(inserted.clone());
+                    None
+                }
+            },
+            Ordering::Less => match self.right {
+                Some(ref right) => Some(right.clone()),
+                None => {
+                    // insert node as right child
+                    self.right = Some(inserted.clone());
+                    None
+                }
+            },
         }
     }
 }
+
+impl<K: Ord + Clone + Debug, V, A: Augment<K, V>> Node<K, V, A> {
+    pub fn bstinsert(&mut self, inserted: &mut Rc<RefCell<Node<K, V, A>>>) -> bool {
+        self.bstinsert_by(inserted, &Natural)
+    }
+}
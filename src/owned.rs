@@ -0,0 +1,260 @@
+use crate::Splayable;
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::fmt::Debug;
+use std::iter::FromIterator;
+
+type Link<K> = Option<Box<OwnedNode<K>>>;
+
+struct OwnedNode<K> {
+    key: K,
+    left: Link<K>,
+    right: Link<K>,
+}
+
+// splay tree that owns its nodes through Box, nodes never leave the tree so
+// keys can only be changed by removing and inserting them again
+pub struct OwnedSplayTree<K> {
+    root: Link<K>,
+    len: usize,
+}
+
+impl<K> Default for OwnedSplayTree<K> {
+    fn default() -> Self {
+        OwnedSplayTree { root: None, len: 0 }
+    }
+}
+
+impl<K> OwnedSplayTree<K> {
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn clear(&mut self) {
+        *self = OwnedSplayTree::default();
+    }
+
+    // borrows the boxes through a stack of references, iterating needs no
+    // &mut so the tree is not splayed
+    pub fn iter(&self) -> Iter<'_, K> {
+        let mut iter = Iter { stack: Vec::new() };
+        iter.push_left_spine(self.root.as_deref());
+        iter
+    }
+}
+
+impl<K: Ord> OwnedSplayTree<K> {
+    pub fn new() -> Self {
+        OwnedSplayTree::default()
+    }
+
+    // return false and keep the tree as it is if key is already there
+    pub fn insert(&mut self, key: K) -> bool {
+        self.splay_key(&key);
+
+        // NOTE: the root is now key or its neighbour, the new box takes the
+        // old root as one child and moves the other child over
+        let mut node = Box::new(OwnedNode {
+            key,
+            left: None,
+            right: None,
+        });
+        if let Some(mut root) = self.root.take() {
+            match node.key.cmp(&root.key) {
+                Ordering::Equal => {
+                    self.root = Some(root);
+                    return false;
+                }
+                Ordering::Less => {
+                    node.left = root.left.take();
+                    node.right = Some(root);
+                }
+                Ordering::Greater => {
+                    node.right = root.right.take();
+                    node.left = Some(root);
+                }
+            }
+        }
+        self.root = Some(node);
+        self.len += 1;
+        true
+    }
+
+    pub fn contains(&mut self, key: &K) -> bool {
+        self.splay_key(key);
+        self.root.as_ref().is_some_and(|root| root.key == *key)
+    }
+
+    // ordinary bst descent that leaves the tree as it is
+    pub fn contains_no_splay(&self, key: &K) -> bool {
+        let mut current = self.root.as_deref();
+        while let Some(node) = current {
+            current = match key.cmp(&node.key) {
+                Ordering::Equal => return true,
+                Ordering::Less => node.left.as_deref(),
+                Ordering::Greater => node.right.as_deref(),
+            };
+        }
+        false
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<K> {
+        if !self.contains(key) {
+            return None;
+        }
+
+        // the removed box hands its left subtree over as the new tree, key is
+        // larger than all of it so splaying key lifts the largest box, whose
+        // free right link takes the right subtree
+        let mut removed = self.root.take().unwrap();
+        self.root = removed.left.take();
+        self.splay_key(key);
+        match self.root {
+            Some(ref mut root) => root.right = removed.right.take(),
+            None => self.root = removed.right.take(),
+        }
+        self.len -= 1;
+        Some(removed.key)
+    }
+
+    // top-down splay, the left and right trees are kept as the nodes that
+    // still miss their right (or left) child and are linked at the end
+    fn splay_key(&mut self, key: &K) {
+        let mut current = match self.root.take() {
+            Some(root) => root,
+            None => return,
+        };
+        let mut left: Vec<Box<OwnedNode<K>>> = Vec::new();
+        let mut right: Vec<Box<OwnedNode<K>>> = Vec::new();
+
+        loop {
+            match key.cmp(&current.key) {
+                Ordering::Less => {
+                    let mut child = match current.left.take() {
+                        Some(child) => child,
+                        None => break,
+                    };
+                    if *key < child.key {
+                        // zig zig, rotate right first
+                        current.left = child.right.take();
+                        child.right = Some(current);
+                        current = child;
+                        child = match current.left.take() {
+                            Some(child) => child,
+                            None => break,
+                        };
+                    }
+                    right.push(current);
+                    current = child;
+                }
+                Ordering::Greater => {
+                    let mut child = match current.right.take() {
+                        Some(child) => child,
+                        None => break,
+                    };
+                    if *key > child.key {
+                        // zig zig, rotate left first
+                        current.right = child.left.take();
+                        child.left = Some(current);
+                        current = child;
+                        child = match current.right.take() {
+                            Some(child) => child,
+                            None => break,
+                        };
+                    }
+                    left.push(current);
+                    current = child;
+                }
+                Ordering::Equal => break,
+            }
+        }
+
+        // NOTE: merge the left and right trees with current, the last node
+        // pushed is the deepest one and takes the subtree of current
+        let mut left_tree = current.left.take();
+        while let Some(mut node) = left.pop() {
+            node.right = left_tree;
+            left_tree = Some(node);
+        }
+        let mut right_tree = current.right.take();
+        while let Some(mut node) = right.pop() {
+            node.left = right_tree;
+            right_tree = Some(node);
+        }
+        current.left = left_tree;
+        current.right = right_tree;
+        self.root = Some(current);
+    }
+}
+
+impl<K: Ord> Splayable<K> for OwnedSplayTree<K> {
+    fn splay(&mut self, key: K) {
+        self.splay_key(&key);
+    }
+}
+
+impl<K: Ord> FromIterator<K> for OwnedSplayTree<K> {
+    fn from_iter<I: IntoIterator<Item = K>>(iter: I) -> Self {
+        let mut tree = OwnedSplayTree::new();
+        for key in iter {
+            tree.insert(key);
+        }
+        tree
+    }
+}
+
+// printed as the set of keys, a derived impl would recurse once per level
+impl<K: Debug> Debug for OwnedSplayTree<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+// the default drop recurses once per level, which overflows the stack on the
+// long paths a splay tree can have
+impl<K> Drop for OwnedSplayTree<K> {
+    fn drop(&mut self) {
+        let mut stack: Vec<Box<OwnedNode<K>>> = self.root.take().into_iter().collect();
+        while let Some(mut node) = stack.pop() {
+            stack.extend(node.left.take());
+            stack.extend(node.right.take());
+        }
+    }
+}
+
+pub struct Iter<'a, K> {
+    stack: Vec<&'a OwnedNode<K>>,
+}
+
+impl<'a, K> Iter<'a, K> {
+    fn push_left_spine(&mut self, mut current: Option<&'a OwnedNode<K>>) {
+        while let Some(node) = current {
+            self.stack.push(node);
+            current = node.left.as_deref();
+        }
+    }
+}
+
+impl<'a, K> Iterator for Iter<'a, K> {
+    type Item = &'a K;
+
+    fn next(&mut self) -> Option<&'a K> {
+        let node = self.stack.pop()?;
+        self.push_left_spine(node.right.as_deref());
+        Some(&node.key)
+    }
+}
+
+impl<'a, K> IntoIterator for &'a OwnedSplayTree<K> {
+    type Item = &'a K;
+    type IntoIter = Iter<'a, K>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
@@ -0,0 +1,309 @@
+use crate::Splayable;
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::fmt::Debug;
+use std::mem;
+use std::rc::Rc;
+
+type Link<K, V> = Option<Rc<PersistentNode<K, V>>>;
+
+#[derive(Clone)]
+struct PersistentNode<K, V> {
+    key: K,
+    value: V,
+    left: Link<K, V>,
+    right: Link<K, V>,
+}
+
+// splay tree whose versions share nodes. nodes are never changed while
+// another version can see them: every node a splay walks through is taken
+// with Rc::make_mut, which copies it only if it is shared and otherwise
+// changes it in place, so the rest of the tree stays shared
+pub struct PersistentSplayTree<K, V = ()> {
+    root: Link<K, V>,
+    len: usize,
+}
+
+impl<K, V> Default for PersistentSplayTree<K, V> {
+    fn default() -> Self {
+        PersistentSplayTree { root: None, len: 0 }
+    }
+}
+
+// cloning shares the whole tree, same as snapshot
+impl<K, V> Clone for PersistentSplayTree<K, V> {
+    fn clone(&self) -> Self {
+        PersistentSplayTree {
+            root: self.root.clone(),
+            len: self.len,
+        }
+    }
+}
+
+impl<K, V> PersistentSplayTree<K, V> {
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    // the current version, later changes of either tree don't show in the
+    // other one
+    pub fn snapshot(&self) -> Self {
+        self.clone()
+    }
+
+    // true if both trees are the same version
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        match (&self.root, &other.root) {
+            (Some(left), Some(right)) => Rc::ptr_eq(left, right),
+            (None, None) => true,
+            _ => false,
+        }
+    }
+
+    // reads the nodes of this version through shared references, nothing is
+    // copied and other versions are not touched
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        let mut iter = Iter { stack: Vec::new() };
+        iter.push_left_spine(self.root.as_deref());
+        iter
+    }
+}
+
+impl<K: Ord + Clone, V: Clone> PersistentSplayTree<K, V> {
+    pub fn new() -> Self {
+        PersistentSplayTree::default()
+    }
+
+    // insert key with value, an existing key keeps its node and gets the new
+    // value, the old one is returned
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        self.splay_key(&key);
+
+        // NOTE: the root is now key or its neighbour. it is owned by this
+        // version after the splay, so make_mut only takes its child without a
+        // copy
+        let mut node = PersistentNode {
+            key,
+            value,
+            left: None,
+            right: None,
+        };
+        if let Some(mut root) = self.root.take() {
+            match node.key.cmp(&root.key) {
+                Ordering::Equal => {
+                    let old = mem::replace(&mut Rc::make_mut(&mut root).value, node.value);
+                    self.root = Some(root);
+                    return Some(old);
+                }
+                Ordering::Less => {
+                    node.left = Rc::make_mut(&mut root).left.take();
+                    node.right = Some(root);
+                }
+                Ordering::Greater => {
+                    node.right = Rc::make_mut(&mut root).right.take();
+                    node.left = Some(root);
+                }
+            }
+        }
+        self.root = Some(Rc::new(node));
+        self.len += 1;
+        None
+    }
+
+    pub fn get(&mut self, key: &K) -> Option<&V> {
+        if self.splay_to_root(key) {
+            self.root.as_ref().map(|root| &root.value)
+        } else {
+            None
+        }
+    }
+
+    // ordinary bst descent, nothing is copied
+    pub fn get_no_splay(&self, key: &K) -> Option<&V> {
+        let mut current = self.root.as_deref();
+        while let Some(node) = current {
+            current = match key.cmp(&node.key) {
+                Ordering::Equal => return Some(&node.value),
+                Ordering::Less => node.left.as_deref(),
+                Ordering::Greater => node.right.as_deref(),
+            };
+        }
+        None
+    }
+
+    pub fn contains_key(&mut self, key: &K) -> bool {
+        self.splay_to_root(key)
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        if !self.splay_to_root(key) {
+            return None;
+        }
+
+        // the value is moved out only if no other version holds the node
+        let (left, right, value) = match Rc::try_unwrap(self.root.take().unwrap()) {
+            Ok(node) => (node.left, node.right, node.value),
+            Err(shared) => (
+                shared.left.clone(),
+                shared.right.clone(),
+                shared.value.clone(),
+            ),
+        };
+
+        // only the left subtree is splayed, copying the nodes on its path that
+        // other versions share. the right subtree is hung back unchanged
+        self.root = left;
+        self.splay_key(key);
+        match self.root {
+            Some(ref mut root) => Rc::make_mut(root).right = right,
+            None => self.root = right,
+        }
+        self.len -= 1;
+        Some(value)
+    }
+
+    fn splay_to_root(&mut self, key: &K) -> bool {
+        self.splay_key(key);
+        self.root.as_ref().is_some_and(|root| root.key == *key)
+    }
+
+    // top-down splay, every node linked into the left or right tree is
+    // copied first if another version shares it
+    //
+    // NOTE: SplayTree, ArenaSplayTree, OwnedSplayTree and this tree each keep
+    // their own copy of this loop. the steps are the same, but Rc<RefCell>,
+    // indices, Box and make_mut move nodes in ways one generic loop can't
+    // cover without cost on every step
+    fn splay_key(&mut self, key: &K) {
+        let mut current = match self.root.take() {
+            Some(root) => root,
+            None => return,
+        };
+        let mut left: Vec<Rc<PersistentNode<K, V>>> = Vec::new();
+        let mut right: Vec<Rc<PersistentNode<K, V>>> = Vec::new();
+
+        loop {
+            match key.cmp(&current.key) {
+                Ordering::Less => {
+                    let mut child = match current.left {
+                        Some(_) => Rc::make_mut(&mut current).left.take().unwrap(),
+                        None => break,
+                    };
+                    if *key < child.key {
+                        // zig zig, rotate right first
+                        Rc::make_mut(&mut current).left = Rc::make_mut(&mut child).right.take();
+                        Rc::make_mut(&mut child).right = Some(current);
+                        current = child;
+                        child = match current.left {
+                            Some(_) => Rc::make_mut(&mut current).left.take().unwrap(),
+                            None => break,
+                        };
+                    }
+                    right.push(current);
+                    current = child;
+                }
+                Ordering::Greater => {
+                    let mut child = match current.right {
+                        Some(_) => Rc::make_mut(&mut current).right.take().unwrap(),
+                        None => break,
+                    };
+                    if *key > child.key {
+                        // zig zig, rotate left first
+                        Rc::make_mut(&mut current).right = Rc::make_mut(&mut child).left.take();
+                        Rc::make_mut(&mut child).left = Some(current);
+                        current = child;
+                        child = match current.right {
+                            Some(_) => Rc::make_mut(&mut current).right.take().unwrap(),
+                            None => break,
+                        };
+                    }
+                    left.push(current);
+                    current = child;
+                }
+                Ordering::Equal => break,
+            }
+        }
+
+        // NOTE: merge the left and right trees with current, the nodes on
+        // both stacks are already owned by this version
+        let root = Rc::make_mut(&mut current);
+        let mut left_tree = root.left.take();
+        while let Some(mut node) = left.pop() {
+            Rc::make_mut(&mut node).right = left_tree;
+            left_tree = Some(node);
+        }
+        let mut right_tree = root.right.take();
+        while let Some(mut node) = right.pop() {
+            Rc::make_mut(&mut node).left = right_tree;
+            right_tree = Some(node);
+        }
+        root.left = left_tree;
+        root.right = right_tree;
+        self.root = Some(current);
+    }
+}
+
+impl<K: Ord + Clone, V: Clone> Splayable<K> for PersistentSplayTree<K, V> {
+    fn splay(&mut self, key: K) {
+        self.splay_key(&key);
+    }
+}
+
+// printed as a map of this version, a derived impl would recurse once per
+// level
+impl<K: Debug, V: Debug> Debug for PersistentSplayTree<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+// nodes still held by another version are left to it, the rest are freed
+// without recursing down long paths
+impl<K, V> Drop for PersistentSplayTree<K, V> {
+    fn drop(&mut self) {
+        let mut stack: Vec<Rc<PersistentNode<K, V>>> = self.root.take().into_iter().collect();
+        while let Some(node) = stack.pop() {
+            if let Ok(mut node) = Rc::try_unwrap(node) {
+                stack.extend(node.left.take());
+                stack.extend(node.right.take());
+            }
+        }
+    }
+}
+
+pub struct Iter<'a, K, V = ()> {
+    stack: Vec<&'a PersistentNode<K, V>>,
+}
+
+impl<'a, K, V> Iter<'a, K, V> {
+    fn push_left_spine(&mut self, mut current: Option<&'a PersistentNode<K, V>>) {
+        while let Some(node) = current {
+            self.stack.push(node);
+            current = node.left.as_deref();
+        }
+    }
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.stack.pop()?;
+        self.push_left_spine(node.right.as_deref());
+        Some((&node.key, &node.value))
+    }
+}
+
+impl<'a, K, V> IntoIterator for &'a PersistentSplayTree<K, V> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
@@ -0,0 +1,24 @@
+// which accesses restructure the tree. every access that is not splayed is a
+// plain descent, so it leaves the links alone and can run under a read lock
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SplayPolicy {
+    // splay on every access
+    #[default]
+    Always,
+    // splay one access out of every n, 0 and 1 splay them all
+    Sampled(usize),
+    // splay only keys found more than this many links below the root
+    DeeperThan(usize),
+}
+
+impl SplayPolicy {
+    // access counts the accesses made before this one, depth is the number of
+    // links walked from the root to the key or to the node it would hang from
+    pub fn should_splay(&self, access: usize, depth: usize) -> bool {
+        match *self {
+            SplayPolicy::Always => true,
+            SplayPolicy::Sampled(every) => access.is_multiple_of(every.max(1)),
+            SplayPolicy::DeeperThan(limit) => depth > limit,
+        }
+    }
+}
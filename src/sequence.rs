@@ -0,0 +1,322 @@
+use crate::augment::sealed::Token;
+use crate::iter;
+use crate::Augment;
+use crate::Node;
+use crate::SplayTree;
+
+use std::cell::Ref;
+use std::cell::RefMut;
+use std::iter::FromIterator;
+use std::mem;
+use std::ops::AddAssign;
+use std::ops::Bound;
+use std::ops::RangeBounds;
+use std::rc::Rc;
+
+// splay tree used as a sequence: nodes are ordered by position only, which is
+// taken from the subtree sizes, so every node carries the unit key
+#[derive(Debug)]
+pub struct SplaySequence<T, A = ()> {
+    tree: SplayTree<(), T, A>,
+}
+
+// lazy tags for range updates on a sequence: the values of the node itself
+// are always current, the tags are still owed to its children
+#[derive(Clone, Debug)]
+pub struct Lazy<T> {
+    // children have to be swapped and reversed themselves
+    reversed: bool,
+    // delta not yet added to the children
+    pending: Option<T>,
+}
+
+impl<T> Default for Lazy<T> {
+    fn default() -> Self {
+        Lazy {
+            reversed: false,
+            pending: None,
+        }
+    }
+}
+
+impl<T: AddAssign + Clone> Lazy<T> {
+    fn reverse(node: &mut Node<(), T, Self>) {
+        node.summary.reversed = !node.summary.reversed;
+    }
+
+    fn add(node: &mut Node<(), T, Self>, delta: &T) {
+        node.value += delta.clone();
+        match node.summary.pending {
+            Some(ref mut pending) => *pending += delta.clone(),
+            None => node.summary.pending = Some(delta.clone()),
+        }
+    }
+}
+
+impl<T: AddAssign + Clone> Augment<(), T> for Lazy<T> {
+    fn combine(_: Option<&Self>, _: &(), _: &T, _: Option<&Self>) -> Self {
+        Lazy::default()
+    }
+
+    fn lazy(_: Token) -> bool {
+        true
+    }
+
+    fn push_down(node: &mut Node<(), T, Self>, _: Token) {
+        if node.summary.reversed {
+            node.summary.reversed = false;
+            mem::swap(&mut node.left, &mut node.right);
+            for child in node.left.iter().chain(node.right.iter()) {
+                Lazy::reverse(&mut child.borrow_mut());
+            }
+        }
+        if let Some(delta) = node.summary.pending.take() {
+            for child in node.left.iter().chain(node.right.iter()) {
+                Lazy::add(&mut child.borrow_mut(), &delta);
+            }
+        }
+    }
+}
+
+impl<T, A> Default for SplaySequence<T, A> {
+    fn default() -> Self {
+        SplaySequence {
+            tree: SplayTree::default(),
+        }
+    }
+}
+
+impl<T> SplaySequence<T> {
+    pub fn new() -> Self {
+        SplaySequence {
+            tree: SplayTree::default(),
+        }
+    }
+}
+
+impl<T, A: Augment<(), T>> SplaySequence<T, A> {
+    pub fn len(&self) -> usize {
+        self.tree.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tree.is_empty()
+    }
+
+    pub fn insert_at(&mut self, index: usize, value: T) {
+        let len = self.len();
+        assert!(
+            index <= len,
+            "insertion index {} is out of bounds for length {}",
+            index,
+            len
+        );
+
+        // NOTE: the node at index is splayed to the root and the new node X
+        // takes its place, appending hangs the old tree under X instead
+        //
+        //       Y              X                Y             X
+        //      / \     =>     / \              /      =>     /
+        //     a   b          a   Y            a             Y
+        //                         \                        /
+        //                          b                      a
+        let inserted = Node::with_value((), value);
+        if index < len {
+            self.tree.splay_at(index);
+            let root = mem::take(&mut self.tree.root).unwrap();
+            inserted.borrow_mut().left = mem::take(&mut root.borrow_mut().left);
+            root.borrow_mut().update();
+            inserted.borrow_mut().right = Some(root);
+        } else if len > 0 {
+            self.tree.splay_at(len - 1);
+            inserted.borrow_mut().left = mem::take(&mut self.tree.root);
+        }
+        inserted.borrow_mut().update();
+        self.tree.root = Some(inserted);
+    }
+
+    pub fn push_back(&mut self, value: T) {
+        self.insert_at(self.len(), value);
+    }
+
+    pub fn push_front(&mut self, value: T) {
+        self.insert_at(0, value);
+    }
+
+    pub fn remove_at(&mut self, index: usize) -> Option<T> {
+        if index >= self.len() {
+            return None;
+        }
+        self.tree.splay_at(index);
+
+        let removed = mem::take(&mut self.tree.root).unwrap();
+        let left = SplaySequence {
+            tree: self
+                .tree
+                .with_root(mem::take(&mut removed.borrow_mut().left)),
+        };
+        let right = SplaySequence {
+            tree: self
+                .tree
+                .with_root(mem::take(&mut removed.borrow_mut().right)),
+        };
+        *self = SplaySequence::concat(left, right);
+
+        // nodes of the sequence are never handed out, so the node is unique here
+        match Rc::try_unwrap(removed) {
+            Ok(node) => Some(node.into_inner().value),
+            Err(_) => unreachable!("sequence node is still shared"),
+        }
+    }
+
+    pub fn get(&mut self, index: usize) -> Option<Ref<'_, T>> {
+        if index >= self.len() {
+            return None;
+        }
+        self.tree.splay_at(index);
+        let root = self.tree.root.as_ref().unwrap();
+        Some(Ref::map(root.borrow(), |node| &node.value))
+    }
+
+    pub fn get_mut(&mut self, index: usize) -> Option<RefMut<'_, T>> {
+        if index >= self.len() {
+            return None;
+        }
+        self.tree.splay_at(index);
+        let root = self.tree.root.as_ref().unwrap();
+        Some(RefMut::map(root.borrow_mut(), |node| &mut node.value))
+    }
+
+    // keep the first index values, return a sequence with the rest
+    pub fn split_off(&mut self, index: usize) -> Self {
+        let len = self.len();
+        assert!(
+            index <= len,
+            "split index {} is out of bounds for length {}",
+            index,
+            len
+        );
+
+        let mut right = SplaySequence::default();
+        if index == len {
+            return right;
+        }
+        self.tree.splay_at(index);
+        let root = self.tree.root.as_ref().unwrap().clone();
+        let left_tree = mem::take(&mut root.borrow_mut().left);
+        root.borrow_mut().update();
+        right.tree.root = mem::replace(&mut self.tree.root, left_tree);
+        right
+    }
+
+    pub fn split_at(mut self, index: usize) -> (Self, Self) {
+        let right = self.split_off(index);
+        (self, right)
+    }
+
+    // every value of left followed by every value of right
+    pub fn concat(mut left: Self, mut right: Self) -> Self {
+        if left.is_empty() {
+            return right;
+        }
+
+        // the last node of left has no right child once it is the root
+        left.tree.splay_at(left.len() - 1);
+        let root = left.tree.root.as_ref().unwrap();
+        root.borrow_mut().right = mem::take(&mut right.tree.root);
+        root.borrow_mut().update();
+        left
+    }
+
+    pub fn append(&mut self, other: &mut Self) {
+        let left = mem::take(self);
+        let right = mem::take(other);
+        *self = SplaySequence::concat(left, right);
+    }
+
+    pub fn iter(&self) -> Iter<'_, T, A> {
+        Iter {
+            inner: self.tree.iter(),
+        }
+    }
+}
+
+impl<T: AddAssign + Clone> SplaySequence<T, Lazy<T>> {
+    // cut the range out, tag the root of the middle part and glue the three
+    // parts back together
+    fn update_range<R, F>(&mut self, range: R, tag: F)
+    where
+        R: RangeBounds<usize>,
+        F: FnOnce(&mut Node<(), T, Lazy<T>>),
+    {
+        let start = match range.start_bound() {
+            Bound::Included(&start) => start,
+            Bound::Excluded(&start) => start + 1,
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(&end) => end + 1,
+            Bound::Excluded(&end) => end,
+            Bound::Unbounded => self.len(),
+        };
+        assert!(
+            start <= end && end <= self.len(),
+            "range {}..{} is out of bounds for length {}",
+            start,
+            end,
+            self.len()
+        );
+
+        let mut tail = self.split_off(end);
+        let mut middle = self.split_off(start);
+        if let Some(ref root) = middle.tree.root {
+            tag(&mut root.borrow_mut());
+        }
+        self.append(&mut middle);
+        self.append(&mut tail);
+    }
+
+    pub fn reverse<R: RangeBounds<usize>>(&mut self, range: R) {
+        self.update_range(range, Lazy::reverse);
+    }
+
+    pub fn add<R: RangeBounds<usize>>(&mut self, range: R, delta: T) {
+        self.update_range(range, |root| Lazy::add(root, &delta));
+    }
+}
+
+impl<T, A: Augment<(), T>> FromIterator<T> for SplaySequence<T, A> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut sequence = SplaySequence::default();
+        for value in iter {
+            sequence.push_back(value);
+        }
+        sequence
+    }
+}
+
+pub struct Iter<'a, T, A = ()> {
+    inner: iter::Iter<'a, (), T, A>,
+}
+
+impl<'a, T: Clone, A: Augment<(), T>> Iterator for Iter<'a, T, A> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.inner.next().map(|node| node.borrow().value.clone())
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, T: Clone, A: Augment<(), T>> DoubleEndedIterator for Iter<'a, T, A> {
+    fn next_back(&mut self) -> Option<T> {
+        self.inner
+            .next_back()
+            .map(|node| node.borrow().value.clone())
+    }
+}
+
+impl<'a, T: Clone, A: Augment<(), T>> ExactSizeIterator for Iter<'a, T, A> {}
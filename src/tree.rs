@@ -1,7 +1,15 @@
+use crate::Augment;
+use crate::Compare;
+use crate::Natural;
 use crate::Node;
+use crate::SplayError;
 use crate::SplayNode;
+use crate::SplayStrategy;
 
+use std::borrow;
 use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::fmt;
 use std::fmt::Debug;
 use std::mem;
 use std::rc::Rc;
@@ -10,9 +18,48 @@ pub trait Splayable<K> {
     fn splay(&mut self, key: K);
 }
 
-#[derive(Clone, Debug)]
-pub struct SplayTree<K> {
-    pub root: SplayNode<K>,
+pub struct SplayTree<K, V = (), A = (), C = Natural> {
+    pub root: SplayNode<K, V, A>,
+    // ordering of the keys, trees split off this one share it
+    cmp: C,
+    // how splays by key run, splays by index are always top-down
+    strategy: SplayStrategy,
+}
+
+// what insert_with does when the key is already in the tree
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DuplicatePolicy {
+    // insert the new node next to the equal keys
+    #[default]
+    Allow,
+    // leave the tree as it is and fail with SplayError::DuplicateKey
+    Reject,
+    // the new node takes the place of the stored one
+    Replace,
+    // leave the tree as it is, the stored node wins
+    KeepFirst,
+}
+
+#[derive(Debug)]
+pub enum InsertOutcome<K, V = (), A = ()> {
+    // the key was not in the tree
+    Inserted,
+    // the node was added next to equal keys
+    Duplicated,
+    // the node replaced the one handed back here
+    Replaced(Rc<RefCell<Node<K, V, A>>>),
+    // the stored node was kept and the new one was not inserted
+    Kept,
+}
+
+// whether a lookup brings the key to the root
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Access {
+    // splay the key, later lookups of it are cheap
+    #[default]
+    Splay,
+    // plain descent that leaves the tree as it is
+    NoSplay,
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -24,154 +71,761 @@ enum SplayCase {
     ZigZigRight,
     ZigZagLeft,
     ZigZagRight,
-    CaseError,
 }
 
-fn splay_case<K: Ord>(nodeptr: &SplayNode<K>, key: K) -> SplayCase {
-    if nodeptr.as_ref().unwrap().as_ref().borrow().key == key {
-        return SplayCase::Merge;
-    } else if nodeptr.as_ref().unwrap().borrow().key < key {
-        // current node is smaller than target, so we head for right tree
-        if nodeptr
-            .as_ref()
-            .unwrap()
-            .borrow()
-            .right
-            .as_ref()
-            .is_some_and(|x| x.borrow().key == key)
-        {
-            return SplayCase::RightRotate;
-        } else if nodeptr
-            .as_ref()
-            .unwrap()
-            .borrow()
-            .right
-            .as_ref()
-            .is_some_and(|x| x.borrow().key > key)
-        {
-            return SplayCase::ZigZagRight;
-        } else if nodeptr
-            .as_ref()
-            .unwrap()
-            .borrow()
-            .right
-            .as_ref()
-            .is_some_and(|x| x.borrow().key < key)
-        {
-            return SplayCase::ZigZigRight;
+fn splay_case<K: borrow::Borrow<Q>, Q: ?Sized, V, A, C: Compare<Q>>(
+    nodeptr: &Rc<RefCell<Node<K, V, A>>>,
+    key: &Q,
+    cmp: &C,
+) -> SplayCase {
+    let node = nodeptr.borrow();
+    match cmp.compare(node.key.borrow(), key) {
+        Ordering::Equal => SplayCase::Merge,
+        Ordering::Less => {
+            // current node is smaller than target, so we head for right tree
+            if let Some(ref right) = node.right {
+                let right = right.borrow();
+                match cmp.compare(right.key.borrow(), key) {
+                    Ordering::Greater if right.left.is_some() => SplayCase::ZigZagRight,
+                    Ordering::Less if right.right.is_some() => SplayCase::ZigZigRight,
+                    // found the target, or it is missing and we stop next to
+                    // where it would be
+                    _ => SplayCase::RightRotate,
+                }
+            } else {
+                SplayCase::Merge
+            }
+        }
+        Ordering::Greater => {
+            // current node is larger than target, so we head for left tree
+            if let Some(ref left) = node.left {
+                let left = left.borrow();
+                match cmp.compare(left.key.borrow(), key) {
+                    Ordering::Greater if left.left.is_some() => SplayCase::ZigZigLeft,
+                    Ordering::Less if left.right.is_some() => SplayCase::ZigZagLeft,
+                    // found the target, or it is missing and we stop next to
+                    // where it would be
+                    _ => SplayCase::LeftRotate,
+                }
+            } else {
+                SplayCase::Merge
+            }
+        }
+    }
+}
+
+// same as splay_case, but the target is the index-th node of the subtree of
+// nodeptr, index is moved along with the descent
+fn index_case<K, V, A>(nodeptr: &Rc<RefCell<Node<K, V, A>>>, index: &mut usize) -> SplayCase {
+    let node = nodeptr.borrow();
+    let left_size = node.left_size();
+    if *index == left_size {
+        SplayCase::Merge
+    } else if *index > left_size {
+        // target is after current node, so we head for right tree
+        if let Some(ref right) = node.right {
+            *index -= left_size + 1;
+            let right = right.borrow();
+            let right_left_size = right.left_size();
+            if *index == right_left_size {
+                SplayCase::RightRotate
+            } else if *index < right_left_size {
+                SplayCase::ZigZagRight
+            } else if right.right.is_some() {
+                *index -= right_left_size + 1;
+                SplayCase::ZigZigRight
+            } else {
+                // index is past the end, stop at the last node
+                SplayCase::RightRotate
+            }
         } else {
-            return SplayCase::Merge;
+            SplayCase::Merge
         }
-    } else if nodeptr.as_ref().unwrap().borrow().key > key {
-        // current node is larger than target, so we head for left tree
-        if nodeptr
-            .as_ref()
-            .unwrap()
-            .borrow()
-            .left
-            .as_ref()
-            .is_some_and(|x| x.borrow().key == key)
-        {
-            return SplayCase::LeftRotate;
-        } else if nodeptr
-            .as_ref()
-            .unwrap()
-            .borrow()
-            .left
-            .as_ref()
-            .is_some_and(|x| x.borrow().key > key)
-        {
-            return SplayCase::ZigZigLeft;
-        } else if nodeptr
-            .as_ref()
-            .unwrap()
-            .borrow()
-            .left
-            .as_ref()
-            .is_some_and(|x| x.borrow().key < key)
-        {
-            return SplayCase::ZigZagLeft;
+    } else {
+        // target is before current node, so we head for left tree
+        let left = node.left.as_ref().unwrap().borrow();
+        let left_left_size = left.left_size();
+        if *index == left_left_size {
+            SplayCase::LeftRotate
+        } else if *index < left_left_size {
+            SplayCase::ZigZigLeft
         } else {
-            return SplayCase::Merge;
+            *index -= left_left_size + 1;
+            SplayCase::ZigZagLeft
+        }
+    }
+}
+
+// nodes of a tree under assembly that still miss one child, top first
+type Spine<K, V, A> = Vec<Rc<RefCell<Node<K, V, A>>>>;
+
+// the child a spine goes down through
+type Side<K, V, A> = fn(&mut Node<K, V, A>) -> &mut SplayNode<K, V, A>;
+
+// link the nodes of an open spine top to bottom, hang end from the last one
+// and update them bottom up, the top node is the assembled tree
+fn close_spine<K: Clone + Debug, V, A: Augment<K, V>>(
+    mut spine: Spine<K, V, A>,
+    end: SplayNode<K, V, A>,
+    side: Side<K, V, A>,
+) -> SplayNode<K, V, A> {
+    let mut tree = end;
+    while let Some(node) = spine.pop() {
+        *side(&mut node.borrow_mut()) = tree;
+        node.borrow_mut().update();
+        tree = Some(node);
+    }
+    tree
+}
+
+fn push_down_children<K, V, A: Augment<K, V>>(nodeptr: &Rc<RefCell<Node<K, V, A>>>) {
+    let mut node = nodeptr.borrow_mut();
+    node.push_down();
+    for child in node.left.iter().chain(node.right.iter()) {
+        child.borrow_mut().push_down();
+    }
+}
+
+fn is_left_child<K, V, A>(
+    child: &Rc<RefCell<Node<K, V, A>>>,
+    parent: &Rc<RefCell<Node<K, V, A>>>,
+) -> bool {
+    let parent = parent.borrow();
+    parent
+        .left
+        .as_ref()
+        .is_some_and(|left| Rc::ptr_eq(left, child))
+}
+
+// point the link of node that goes to old at new instead
+fn relink<K, V, A>(
+    node: &Rc<RefCell<Node<K, V, A>>>,
+    old: &Rc<RefCell<Node<K, V, A>>>,
+    new: &Rc<RefCell<Node<K, V, A>>>,
+) {
+    let left = is_left_child(old, node);
+    let mut node = node.borrow_mut();
+    let link = if left {
+        &mut node.left
+    } else {
+        &mut node.right
+    };
+    *link = Some(new.clone());
+}
+
+// rotate child above parent and update both, whatever pointed at parent has
+// to be relinked by the caller
+fn rotate_up<K, V, A: Augment<K, V>>(
+    child: &Rc<RefCell<Node<K, V, A>>>,
+    parent: &Rc<RefCell<Node<K, V, A>>>,
+) {
+    let left = is_left_child(child, parent);
+    {
+        let mut parent_node = parent.borrow_mut();
+        let mut child_node = child.borrow_mut();
+        if left {
+            parent_node.left = mem::take(&mut child_node.right);
+            child_node.right = Some(parent.clone());
+        } else {
+            parent_node.right = mem::take(&mut child_node.left);
+            child_node.left = Some(parent.clone());
+        }
+        parent_node.update();
+    }
+    child.borrow_mut().update();
+}
+
+// clones copy every node, use shallow_share to get a tree on the same nodes
+impl<K: Clone, V: Clone, A: Clone, C: Clone> Clone for SplayTree<K, V, A, C> {
+    fn clone(&self) -> Self {
+        SplayTree {
+            root: self
+                .root
+                .as_ref()
+                .map(|root| Rc::new(RefCell::new(root.borrow().clone()))),
+            cmp: self.cmp.clone(),
+            strategy: self.strategy,
+        }
+    }
+}
+
+// nodes are listed in key order by an in-order walk with a stack, a derived
+// impl would recurse once per level. the comparator is left out, closures
+// can't be printed
+impl<K: Debug, V: Debug, A: Debug, C> Debug for SplayTree<K, V, A, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SplayTree")
+            .field("nodes", &InOrder(&self.root))
+            .finish()
+    }
+}
+
+struct InOrder<'a, K, V, A>(&'a SplayNode<K, V, A>);
+
+impl<'a, K: Debug, V: Debug, A: Debug> Debug for InOrder<'a, K, V, A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut list = f.debug_list();
+        let mut stack = Vec::new();
+        let mut current = self.0.clone();
+        loop {
+            while let Some(node) = current {
+                current = node.borrow().left.clone();
+                stack.push(node);
+            }
+            let node = match stack.pop() {
+                Some(node) => node,
+                None => break,
+            };
+            list.entry(&*node.borrow());
+            current = node.borrow().right.clone();
+        }
+        list.finish()
+    }
+}
+
+// dropping the root Rc would free the nodes recursively, one stack frame per
+// level, so nodes only this tree holds are taken apart one at a time
+//
+// NOTE: a node that is still held elsewhere, like one returned by get, keeps
+// its subtree. dropping that last handle after the tree frees the subtree
+// recursively again, so handles into deep trees should go first
+impl<K, V, A, C> Drop for SplayTree<K, V, A, C> {
+    fn drop(&mut self) {
+        let mut stack: Vec<Rc<RefCell<Node<K, V, A>>>> = self.root.take().into_iter().collect();
+        while let Some(node) = stack.pop() {
+            if let Ok(node) = Rc::try_unwrap(node) {
+                let mut node = node.into_inner();
+                stack.extend(node.left.take());
+                stack.extend(node.right.take());
+            }
+        }
+    }
+}
+
+impl<K, V, A, C: Default> Default for SplayTree<K, V, A, C> {
+    fn default() -> Self {
+        SplayTree {
+            root: None,
+            cmp: C::default(),
+            strategy: SplayStrategy::TopDown,
         }
     }
-    SplayCase::CaseError
 }
 
-impl<K: Ord + Clone + Debug> SplayTree<K> {
+// augmented trees are created through Default, so that new can pin the
+// summary type of plain trees
+impl<K: Ord + Clone + Debug, V> SplayTree<K, V> {
     pub fn new() -> Self {
-        SplayTree { root: None }
+        SplayTree {
+            root: None,
+            cmp: Natural,
+            strategy: SplayStrategy::TopDown,
+        }
+    }
+}
+
+// keys are ordered by cmp instead of Ord, cmp can be a closure or any type
+// implementing Compare
+impl<K: Clone + Debug, V, C: Compare<K>> SplayTree<K, V, (), C> {
+    pub fn with_comparator(cmp: C) -> Self {
+        SplayTree {
+            root: None,
+            cmp,
+            strategy: SplayStrategy::TopDown,
+        }
+    }
+}
+
+impl<K: Clone + Debug, V, A: Augment<K, V>, C: Compare<K> + Clone> SplayTree<K, V, A, C> {
+    // with_comparator for augmented trees, the summary type comes from the
+    // type of the tree
+    pub fn augmented_with_comparator(cmp: C) -> Self {
+        SplayTree {
+            root: None,
+            cmp,
+            strategy: SplayStrategy::TopDown,
+        }
+    }
+
+    pub fn comparator(&self) -> &C {
+        &self.cmp
+    }
+
+    pub fn strategy(&self) -> SplayStrategy {
+        self.strategy
+    }
+
+    // trees split off this one take the strategy along
+    pub fn set_strategy(&mut self, strategy: SplayStrategy) {
+        self.strategy = strategy;
     }
 
-    pub fn insert(&mut self, inserted: &mut Rc<RefCell<Node<K>>>) {
+    // tree on the same nodes as self, splaying one of them moves the root of
+    // the other out from under it, so only read through the second one or
+    // set its root again after every change
+    pub fn shallow_share(&self) -> Self {
+        self.with_root(self.root.clone())
+    }
+
+    // empty tree, or a tree around root, ordered like self
+    pub(crate) fn with_root(&self, root: SplayNode<K, V, A>) -> Self {
+        SplayTree {
+            root,
+            cmp: self.cmp.clone(),
+            strategy: self.strategy,
+        }
+    }
+
+    // insert next to any equal keys, same as DuplicatePolicy::Allow
+    pub fn insert(&mut self, inserted: &mut Rc<RefCell<Node<K, V, A>>>) -> InsertOutcome<K, V, A> {
         if self.root.is_none() {
             self.root = Some(inserted.to_owned());
-            return;
+            return InsertOutcome::Inserted;
         }
 
-        self.root.as_ref().unwrap().borrow_mut().bstinsert(inserted);
+        let duplicated = self
+            .root
+            .as_ref()
+            .unwrap()
+            .borrow_mut()
+            .bstinsert_by(inserted, &self.cmp);
 
         // BUG: self.splay(inserted.borrow().key.clone())
         let key = inserted.borrow().key.clone();
         self.splay(key);
+
+        if duplicated {
+            InsertOutcome::Duplicated
+        } else {
+            InsertOutcome::Inserted
+        }
     }
 
+    pub fn insert_with(
+        &mut self,
+        inserted: &mut Rc<RefCell<Node<K, V, A>>>,
+        policy: DuplicatePolicy,
+    ) -> Result<InsertOutcome<K, V, A>, SplayError> {
+        if policy == DuplicatePolicy::Allow {
+            return Ok(self.insert(inserted));
+        }
+
+        let key = inserted.borrow().key.clone();
+        if !self.splay_to_root(&key) {
+            self.insert_root(inserted.clone());
+            return Ok(InsertOutcome::Inserted);
+        }
+
+        let root = self.root.clone().unwrap();
+        match policy {
+            DuplicatePolicy::Reject => Err(SplayError::DuplicateKey),
+            DuplicatePolicy::KeepFirst => Ok(InsertOutcome::Kept),
+            _ => {
+                // NOTE: inserted takes the links of the old root, which
+                // leaves the tree
+                inserted.borrow_mut().left = mem::take(&mut root.borrow_mut().left);
+                inserted.borrow_mut().right = mem::take(&mut root.borrow_mut().right);
+                root.borrow_mut().update();
+                inserted.borrow_mut().update();
+                self.root = Some(inserted.clone());
+                Ok(InsertOutcome::Replaced(root))
+            }
+        }
+    }
+
+    // delete one node holding key, a missing key leaves the tree as it is
     pub fn delete(&mut self, key: K) {
-        if self.root.is_none() {
-            return;
+        let _ = self.try_delete(&key);
+    }
+
+    // lookups below take any borrowed form of the key, so a tree of String
+    // can be searched with a &str without cloning anything
+    pub fn try_delete<Q: ?Sized>(&mut self, key: &Q) -> Result<(), SplayError>
+    where
+        K: borrow::Borrow<Q>,
+        C: Compare<Q>,
+    {
+        self.try_splay(key)?;
+        if !self.root_is(key) {
+            return Err(SplayError::KeyNotFound);
         }
 
-        self.splay(key.clone());
-        if self.root.as_ref().unwrap().borrow().key != key {
-            return;
+        self.detach_root();
+        Ok(())
+    }
+
+    // unlink one node holding key and hand it back
+    pub fn take_node<Q: ?Sized>(&mut self, key: &Q) -> SplayNode<K, V, A>
+    where
+        K: borrow::Borrow<Q>,
+        C: Compare<Q>,
+    {
+        if !self.splay_to_root(key) {
+            return None;
+        }
+        self.detach_root()
+    }
+
+    // remove one node holding key, return the key it stored. the key is moved
+    // out when nobody else holds the node and cloned otherwise
+    pub fn take<Q: ?Sized>(&mut self, key: &Q) -> Option<K>
+    where
+        K: borrow::Borrow<Q>,
+        C: Compare<Q>,
+    {
+        let removed = self.take_node(key)?;
+        match Rc::try_unwrap(removed) {
+            Ok(node) => Some(node.into_inner().key),
+            Err(node) => Some(node.borrow().key.clone()),
+        }
+    }
+
+    // same as take
+    pub fn remove<Q: ?Sized>(&mut self, key: &Q) -> Option<K>
+    where
+        K: borrow::Borrow<Q>,
+        C: Compare<Q>,
+    {
+        self.take(key)
+    }
+
+    // splay key to the root and return its node
+    pub fn get<Q: ?Sized>(&mut self, key: &Q) -> SplayNode<K, V, A>
+    where
+        K: borrow::Borrow<Q>,
+        C: Compare<Q>,
+    {
+        if !self.splay_to_root(key) {
+            return None;
+        }
+        self.root.clone()
+    }
+
+    // choose per call whether the lookup restructures the tree
+    pub fn get_with<Q: ?Sized>(&mut self, key: &Q, access: Access) -> SplayNode<K, V, A>
+    where
+        K: borrow::Borrow<Q>,
+        C: Compare<Q>,
+    {
+        match access {
+            Access::Splay => self.get(key),
+            Access::NoSplay => self.get_no_splay(key),
+        }
+    }
+
+    // ordinary bst descent, works through a shared reference
+    pub fn get_no_splay<Q: ?Sized>(&self, key: &Q) -> SplayNode<K, V, A>
+    where
+        K: borrow::Borrow<Q>,
+        C: Compare<Q>,
+    {
+        let mut current = self.root.clone();
+        while let Some(node) = current {
+            let order = self.cmp.compare(node.borrow().key.borrow(), key);
+            current = match order {
+                Ordering::Equal => return Some(node),
+                Ordering::Greater => node.borrow().left.clone(),
+                Ordering::Less => node.borrow().right.clone(),
+            };
+        }
+        None
+    }
+
+    pub fn contains_no_splay<Q: ?Sized>(&self, key: &Q) -> bool
+    where
+        K: borrow::Borrow<Q>,
+        C: Compare<Q>,
+    {
+        self.get_no_splay(key).is_some()
+    }
+
+    pub fn contains<Q: ?Sized>(&mut self, key: &Q) -> bool
+    where
+        K: borrow::Borrow<Q>,
+        C: Compare<Q>,
+    {
+        self.splay_to_root(key)
+    }
+
+    // same as Splayable::splay, without taking the key by value
+    pub fn splay_key<Q: ?Sized>(&mut self, key: &Q)
+    where
+        K: borrow::Borrow<Q>,
+        C: Compare<Q>,
+    {
+        let _ = self.try_splay(key);
+    }
+
+    // splay key, or its neighbour when key is missing, to the root
+    pub fn try_splay<Q: ?Sized>(&mut self, key: &Q) -> Result<(), SplayError>
+    where
+        K: borrow::Borrow<Q>,
+        C: Compare<Q>,
+    {
+        let cmp = &self.cmp;
+        match self.strategy {
+            SplayStrategy::TopDown => {
+                Self::splay_with(&mut self.root, |nodeptr| splay_case(nodeptr, key, cmp))
+            }
+            SplayStrategy::BottomUp => Self::splay_bottom_up(&mut self.root, key, cmp),
         }
+    }
+
+    // splay key to the root, return true if the root now holds key
+    pub(crate) fn splay_to_root<Q: ?Sized>(&mut self, key: &Q) -> bool
+    where
+        K: borrow::Borrow<Q>,
+        C: Compare<Q>,
+    {
+        self.splay_key(key);
+        self.root_is(key)
+    }
 
+    fn root_is<Q: ?Sized>(&self, key: &Q) -> bool
+    where
+        K: borrow::Borrow<Q>,
+        C: Compare<Q>,
+    {
+        self.root.as_ref().is_some_and(|root| {
+            self.cmp.compare(root.borrow().key.borrow(), key) == Ordering::Equal
+        })
+    }
+
+    // link inserted as the new root, the tree has to be splayed around its key
+    pub(crate) fn insert_root(&mut self, inserted: Rc<RefCell<Node<K, V, A>>>) {
+        // NOTE: key is missing, so the splayed root is its neighbour and
+        // the new node becomes the root by splitting the tree around it
+        //
+        //     Y              X               Y              X
+        //    / \     =>     / \             / \     =>     / \
+        //   a   b          Y   b           a   b          a   Y
+        //                 /                                    \
+        //                a                                      b
+        if let Some(root) = mem::take(&mut self.root) {
+            if self.cmp.compare(&root.borrow().key, &inserted.borrow().key) == Ordering::Less {
+                inserted.borrow_mut().right = mem::take(&mut root.borrow_mut().right);
+                root.borrow_mut().update();
+                inserted.borrow_mut().left = Some(root);
+            } else {
+                inserted.borrow_mut().left = mem::take(&mut root.borrow_mut().left);
+                root.borrow_mut().update();
+                inserted.borrow_mut().right = Some(root);
+            }
+        }
+        inserted.borrow_mut().update();
+        self.root = Some(inserted);
+    }
+
+    // unlink the current root and join its left and right subtrees
+    pub(crate) fn detach_root(&mut self) -> SplayNode<K, V, A> {
         let delete_node = mem::take(&mut self.root);
-        let left_tree = mem::take(&mut delete_node.as_ref().unwrap().borrow_mut().left);
-        let right_tree = mem::take(&mut delete_node.as_ref().unwrap().borrow_mut().right);
+        let left_tree = mem::take(&mut delete_node.as_ref()?.borrow_mut().left);
+        let right_tree = mem::take(&mut delete_node.as_ref()?.borrow_mut().right);
+        delete_node.as_ref()?.borrow_mut().update();
+
+        let mut joined = SplayTree::join(self.with_root(left_tree), self.with_root(right_tree));
+        self.root = mem::take(&mut joined.root);
 
-        let left_right_most = left_tree.as_ref().unwrap().borrow().right_most_key();
-        self.root = left_tree;
-        self.splay(left_right_most);
-        self.root.as_ref().unwrap().borrow_mut().right = right_tree;
+        delete_node
     }
 
-    pub fn pop_left_most(&mut self) -> SplayNode<K> {
+    // join two trees where every key of left is not larger than any key of right
+    pub fn join(mut left: Self, mut right: Self) -> Self {
+        if left.root.is_none() {
+            return right;
+        }
+
+        // NOTE: splay the largest key of left to its root, which leaves the
+        // right child free for the right tree
+        //
+        //      L        R              M
+        //     /\       /\     =>      / \
+        //   ... M     .. ..          a   R
+        //      /                        /\
+        //     a                       .. ..
+        let left_right_most = left.root.as_ref().unwrap().borrow().right_most_key();
+        left.splay(left_right_most);
+
+        // equal keys may still hang off the right side of the new root
+        left.root
+            .as_ref()
+            .unwrap()
+            .borrow_mut()
+            .insert_right_most(mem::take(&mut right.root));
+        left
+    }
+
+    // split into keys smaller than key and keys not smaller than key
+    pub fn split(mut self, key: K) -> (Self, Self) {
+        let right = self.split_off(&key);
+        (self, right)
+    }
+
+    // keep keys smaller than key, return a tree with the rest
+    pub fn split_off(&mut self, key: &K) -> Self {
+        let mut right = self.with_root(None);
         if self.root.is_none() {
-            return None;
+            return right;
+        }
+
+        // NOTE: after splaying, the root X sits next to the cut, so only one
+        // of its links has to be broken
+        //
+        //   X < key:   a - X | b        X >= key:   a | X - b
+        self.splay_key(key);
+        let root = self.root.as_ref().unwrap().clone();
+        if self.cmp.compare(&root.borrow().key, key) == Ordering::Less {
+            right.root = mem::take(&mut root.borrow_mut().right);
+        } else {
+            let left_tree = mem::take(&mut root.borrow_mut().left);
+            right.root = mem::replace(&mut self.root, left_tree);
+        }
+        root.borrow_mut().update();
+
+        // duplicates of key can be left behind in the smaller tree, move them
+        // over one at a time
+        while self.splay_to_root(key) {
+            let root = self.root.as_ref().unwrap().clone();
+            let left_tree = mem::take(&mut root.borrow_mut().left);
+            root.borrow_mut().update();
+            let moved = mem::replace(&mut self.root, left_tree);
+            let moved = self.with_root(moved);
+            right = SplayTree::join(moved, right);
+        }
+
+        right
+    }
+
+    // move every key of other into self, other is left empty
+    pub fn append(&mut self, other: &mut Self) {
+        if other.root.is_none() {
+            return;
+        }
+        if self.root.is_none() {
+            mem::swap(&mut self.root, &mut other.root);
+            return;
+        }
+
+        // true if no key of first comes after a key of second
+        let cmp = &self.cmp;
+        let not_after = |first: &SplayNode<K, V, A>, second: &SplayNode<K, V, A>| {
+            let last = first.as_ref().unwrap().borrow().right_most_key();
+            let next = second.as_ref().unwrap().borrow().left_most_key();
+            cmp.compare(&last, &next) != Ordering::Greater
+        };
+        let in_order = not_after(&self.root, &other.root);
+        let reversed = not_after(&other.root, &self.root);
+
+        let left = mem::take(&mut self.root);
+        let left = self.with_root(left);
+        let right = self.with_root(mem::take(&mut other.root));
+        if in_order {
+            *self = SplayTree::join(left, right);
+        } else if reversed {
+            *self = SplayTree::join(right, left);
+        } else {
+            // key ranges overlap, fall back to inserting one node at a time
+            *self = left;
+            for mut node in right {
+                self.insert(&mut node);
+            }
         }
+    }
 
-        // Rc clone
-        let mut current = &mut self.root.clone();
-        let mut parent: &mut SplayNode<K> = &mut None;
+    pub fn pop_left_most(&mut self) -> SplayNode<K, V, A> {
+        // walk down the left spine, every node on the way loses one descendant
+        let mut path = vec![self.root.clone()?];
         loop {
-            if current.as_ref().unwrap().borrow().left.is_none() && parent.is_none() {
-                let left_most = mem::take(&mut self.root);
-                let right_tree = mem::take(&mut left_most.as_ref().unwrap().borrow_mut().right);
-                self.root = right_tree;
-                return left_most;
-            } else if current.as_ref().unwrap().borrow().left.is_none() && parent.is_some() {
-                let left_most = mem::take(current);
-                let right_tree = mem::take(&mut left_most.as_ref().unwrap().borrow_mut().right);
-                parent.as_ref().unwrap().borrow_mut().left = right_tree;
-                return left_most;
-            } else if current.as_ref().unwrap().borrow().left.is_some() {
-                let left_tree = mem::take(&mut current.as_ref().unwrap().borrow_mut().left);
-                let mut old_current = mem::replace(current, left_tree);
-                if parent.is_some() {
-                    mem::swap(parent, &mut old_current);
-                    old_current.as_mut().unwrap().borrow_mut().left = parent.clone();
-                    *parent = old_current.as_ref().unwrap().borrow_mut().left.clone();
-                } else {
-                    *parent = old_current;
-                }
+            let left = path.last().unwrap().borrow().left.clone();
+            match left {
+                Some(left) => path.push(left),
+                None => break,
+            }
+        }
+
+        let left_most = path.pop().unwrap();
+        let right_tree = mem::take(&mut left_most.borrow_mut().right);
+        left_most.borrow_mut().update();
+        if let Some(parent) = path.last() {
+            parent.borrow_mut().left = right_tree;
+        } else {
+            self.root = right_tree;
+        }
+        for node in path.iter().rev() {
+            node.borrow_mut().update();
+        }
+
+        Some(left_most)
+    }
+
+    pub fn len(&self) -> usize {
+        self.root.as_ref().map_or(0, |root| root.borrow().size)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.root.is_none()
+    }
+
+    // number of keys smaller than key
+    pub fn rank(&mut self, key: &K) -> usize {
+        self.splay_key(key);
+
+        let mut rank = 0;
+        let mut current = self.root.clone();
+        while let Some(node) = current {
+            let node = node.borrow();
+            if self.cmp.compare(&node.key, key) == Ordering::Less {
+                rank += node.left_size() + 1;
+                current = node.right.clone();
+            } else {
+                current = node.left.clone();
             }
         }
+        rank
+    }
+
+    // splay the index-th node, counted from 0, to the root
+    pub fn splay_at(&mut self, index: usize) {
+        let mut index = index;
+        let _ = Self::splay_with(&mut self.root, |nodeptr| index_case(nodeptr, &mut index));
+    }
+
+    // the node holding the index-th smallest key, counted from 0
+    pub fn select(&mut self, index: usize) -> SplayNode<K, V, A> {
+        if index >= self.len() {
+            return None;
+        }
+        self.splay_at(index);
+        self.root.clone()
     }
 }
 
-impl<K: Ord + Clone + Debug> IntoIterator for SplayTree<K> {
-    type Item = Rc<RefCell<Node<K>>>;
+impl<K: Clone + Debug, V: Clone, A: Augment<K, V>, C: Compare<K> + Clone> SplayTree<K, V, A, C> {
+    // remove one node holding key, return its key and value. they are moved
+    // out when nobody else holds the node and cloned otherwise
+    pub fn remove_entry<Q: ?Sized>(&mut self, key: &Q) -> Option<(K, V)>
+    where
+        K: borrow::Borrow<Q>,
+        C: Compare<Q>,
+    {
+        let removed = self.take_node(key)?;
+        match Rc::try_unwrap(removed) {
+            Ok(node) => {
+                let node = node.into_inner();
+                Some((node.key, node.value))
+            }
+            Err(node) => {
+                let node = node.borrow();
+                Some((node.key.clone(), node.value.clone()))
+            }
+        }
+    }
+}
+
+impl<K: Clone + Debug, V, A: Augment<K, V>, C: Compare<K> + Clone> IntoIterator
+    for SplayTree<K, V, A, C>
+{
+    type Item = Rc<RefCell<Node<K, V, A>>>;
     type IntoIter = std::vec::IntoIter<Self::Item>;
 
     fn into_iter(mut self) -> Self::IntoIter {
@@ -185,58 +839,51 @@ impl<K: Ord + Clone + Debug> IntoIterator for SplayTree<K> {
     }
 }
 
+impl<K: Clone + Debug, V, A: Augment<K, V>, C: Compare<K> + Clone> Splayable<K>
+    for SplayTree<K, V, A, C>
+{
+    // an empty tree or a failed splay leaves the tree as it was, try_splay
+    // reports them
+    fn splay(&mut self, key: K) {
+        let _ = self.try_splay(&key);
+    }
+}
+
 // implementation of top-down splay algorithm based on:
 //      http://ccf.ee.ntu.edu.tw/~yen/courses/ds17/chapter-4c.pdf
-impl<K: Ord + Clone + Debug> Splayable<K> for SplayTree<K> {
-    fn splay(&mut self, key: K) {
-        if self.root.is_none() {
-            return;
-        }
+impl<K: Clone + Debug, V, A: Augment<K, V>, C: Compare<K> + Clone> SplayTree<K, V, A, C> {
+    // case picks the next step from the current node, which lets the same
+    // loop descend by key or by position
+    //
+    // it works on the root alone, so that case can borrow the comparator of
+    // the tree
+    fn splay_with<F: FnMut(&Rc<RefCell<Node<K, V, A>>>) -> SplayCase>(
+        root: &mut SplayNode<K, V, A>,
+        mut case: F,
+    ) -> Result<(), SplayError> {
+        let mut nodeptr = match mem::take(root) {
+            Some(node) => node,
+            None => return Err(SplayError::EmptyTree),
+        };
 
-        let mut new_left_tree = None;
-        let mut new_right_tree = None;
-        let mut nodeptr = mem::take(&mut self.root);
+        // NOTE: L and R are kept as the nodes on their open spines, top
+        // first. the last one is the tail a new node hangs from, so adding
+        // to L or R is a push. the links along a spine and the sizes on it
+        // are only filled in by merge, once the subtrees below are known
+        let mut new_left_tree: Spine<K, V, A> = Vec::new();
+        let mut new_right_tree: Spine<K, V, A> = Vec::new();
 
         loop {
-            match splay_case(&nodeptr, key.clone()) {
+            // NOTE: lazy tags are pushed below X and its children before the
+            // case looks at them, so every rotation below works on nodes
+            // without pending tags and the grandchild is handled on the next
+            // round once it becomes X. case only picks a step whose child
+            // and grandchild are there, so the rotations unwrap them
+            push_down_children(&nodeptr);
+            match case(&nodeptr) {
                 SplayCase::Merge => {
-                    // NOTE: merge new_left_tree, new_right_tree to nodeptr X
-                    //
-                    // L     X    R                X
-                    //  \   /\   /   =>          /  \
-                    //   a b  c d              a     d
-                    //                          \   /
-                    //                           b c
-                    let mut right_tree =
-                        mem::take(&mut nodeptr.as_ref().unwrap().borrow_mut().right);
-                    let mut left_tree = mem::take(&mut nodeptr.as_ref().unwrap().borrow_mut().left);
-
-                    if new_right_tree.is_none() {
-                        new_right_tree = right_tree;
-                    } else {
-                        new_right_tree
-                            .as_ref()
-                            .unwrap()
-                            .borrow_mut()
-                            .insert_left_most(right_tree);
-                    }
-
-                    if new_left_tree.is_none() {
-                        new_left_tree = left_tree;
-                    } else {
-                        new_left_tree
-                            .as_ref()
-                            .unwrap()
-                            .borrow_mut()
-                            .insert_right_most(left_tree);
-                    }
-
-                    nodeptr.as_ref().unwrap().borrow_mut().right = new_right_tree;
-                    nodeptr.as_ref().unwrap().borrow_mut().left = new_left_tree;
-
-                    self.root = nodeptr;
-
-                    break;
+                    *root = Some(Self::merge(nodeptr, new_left_tree, new_right_tree));
+                    return Ok(());
                 }
                 SplayCase::LeftRotate => {
                     // NOTE:find key X, left rotate X to root
@@ -246,18 +893,8 @@ impl<K: Ord + Clone + Debug> Splayable<K> for SplayTree<K> {
                     //     X  c                  a   b   Y
                     //    /\                              \
                     //   a  b                              c
-                    let mut left_tree = mem::take(&mut nodeptr.as_ref().unwrap().borrow_mut().left);
-
-                    if new_right_tree.is_none() {
-                        new_right_tree = nodeptr;
-                    } else {
-                        new_right_tree
-                            .as_ref()
-                            .unwrap()
-                            .borrow_mut()
-                            .insert_left_most(nodeptr);
-                    }
-
+                    let left_tree = mem::take(&mut nodeptr.borrow_mut().left).unwrap();
+                    new_right_tree.push(nodeptr);
                     nodeptr = left_tree;
                 }
                 SplayCase::RightRotate => {
@@ -268,19 +905,8 @@ impl<K: Ord + Clone + Debug> Splayable<K> for SplayTree<K> {
                     //     c  X               Y   a   b
                     //       /\                \
                     //      a  b                c
-                    let mut right_tree =
-                        mem::take(&mut nodeptr.as_ref().unwrap().borrow_mut().right);
-
-                    if new_left_tree.is_none() {
-                        new_left_tree = nodeptr;
-                    } else {
-                        new_left_tree
-                            .as_ref()
-                            .unwrap()
-                            .borrow_mut()
-                            .insert_right_most(nodeptr);
-                    }
-
+                    let right_tree = mem::take(&mut nodeptr.borrow_mut().right).unwrap();
+                    new_left_tree.push(nodeptr);
                     nodeptr = right_tree;
                 }
                 SplayCase::ZigZigLeft => {
@@ -293,25 +919,14 @@ impl<K: Ord + Clone + Debug> Splayable<K> for SplayTree<K> {
                     //   X  c                              Z
                     //                                    / \
                     //                                   c  d
-                    let mut left_tree = mem::take(&mut nodeptr.as_ref().unwrap().borrow_mut().left);
-                    let mut left_left_tree =
-                        mem::take(&mut left_tree.as_ref().unwrap().borrow_mut().left);
-                    let mut left_right_tree =
-                        mem::take(&mut left_tree.as_ref().unwrap().borrow_mut().right);
-
-                    nodeptr.as_ref().unwrap().borrow_mut().left = left_right_tree;
-                    left_tree.as_ref().unwrap().borrow_mut().right = nodeptr;
-
-                    if new_right_tree.is_none() {
-                        new_right_tree = left_tree;
-                    } else {
-                        new_right_tree
-                            .as_ref()
-                            .unwrap()
-                            .borrow_mut()
-                            .insert_left_most(left_tree);
-                    }
+                    let left_tree = mem::take(&mut nodeptr.borrow_mut().left).unwrap();
+                    let left_left_tree = mem::take(&mut left_tree.borrow_mut().left).unwrap();
+                    let left_right_tree = mem::take(&mut left_tree.borrow_mut().right);
 
+                    nodeptr.borrow_mut().left = left_right_tree;
+                    nodeptr.borrow_mut().update();
+                    left_tree.borrow_mut().right = Some(nodeptr);
+                    new_right_tree.push(left_tree);
                     nodeptr = left_left_tree;
                 }
                 SplayCase::ZigZigRight => {
@@ -324,26 +939,14 @@ impl<K: Ord + Clone + Debug> Splayable<K> for SplayTree<K> {
                     //      c  X            Z
                     //                     / \
                     //                    d   c
-                    let mut right_tree =
-                        mem::take(&mut nodeptr.as_ref().unwrap().borrow_mut().right);
-                    let mut right_right_tree =
-                        mem::take(&mut right_tree.as_ref().unwrap().borrow_mut().right);
-                    let mut right_left_tree =
-                        mem::take(&mut right_tree.as_ref().unwrap().borrow_mut().left);
-
-                    nodeptr.as_ref().unwrap().borrow_mut().right = right_left_tree;
-                    right_tree.as_ref().unwrap().borrow_mut().left = nodeptr;
-
-                    if new_left_tree.is_none() {
-                        new_left_tree = right_tree;
-                    } else {
-                        new_left_tree
-                            .as_ref()
-                            .unwrap()
-                            .borrow_mut()
-                            .insert_right_most(right_tree);
-                    }
+                    let right_tree = mem::take(&mut nodeptr.borrow_mut().right).unwrap();
+                    let right_right_tree = mem::take(&mut right_tree.borrow_mut().right).unwrap();
+                    let right_left_tree = mem::take(&mut right_tree.borrow_mut().left);
 
+                    nodeptr.borrow_mut().right = right_left_tree;
+                    nodeptr.borrow_mut().update();
+                    right_tree.borrow_mut().left = Some(nodeptr);
+                    new_left_tree.push(right_tree);
                     nodeptr = right_right_tree;
                 }
                 SplayCase::ZigZagLeft => {
@@ -354,29 +957,10 @@ impl<K: Ord + Clone + Debug> Splayable<K> for SplayTree<K> {
                     //     Y  d               Y    a   b   Z
                     //    /\                  /            \
                     //   c  X                c              d
-                    let mut left_tree = mem::take(&mut nodeptr.as_ref().unwrap().borrow_mut().left);
-                    let mut left_right_tree =
-                        mem::take(&mut left_tree.as_ref().unwrap().borrow_mut().right);
-
-                    if new_left_tree.is_none() {
-                        new_left_tree = left_tree;
-                    } else {
-                        new_left_tree
-                            .as_ref()
-                            .unwrap()
-                            .borrow_mut()
-                            .insert_left_most(left_tree);
-                    }
-                    if new_right_tree.is_none() {
-                        new_right_tree = nodeptr;
-                    } else {
-                        new_right_tree
-                            .as_ref()
-                            .unwrap()
-                            .borrow_mut()
-                            .insert_left_most(nodeptr);
-                    }
-
+                    let left_tree = mem::take(&mut nodeptr.borrow_mut().left).unwrap();
+                    let left_right_tree = mem::take(&mut left_tree.borrow_mut().right).unwrap();
+                    new_left_tree.push(left_tree);
+                    new_right_tree.push(nodeptr);
                     nodeptr = left_right_tree;
                 }
                 SplayCase::ZigZagRight => {
@@ -387,37 +971,98 @@ impl<K: Ord + Clone + Debug> Splayable<K> for SplayTree<K> {
                     //     c  Y               Z    a   b   Y
                     //       /\               /            \
                     //      X  d             c              d
-                    let mut right_tree =
-                        mem::take(&mut nodeptr.as_ref().unwrap().borrow_mut().right);
-                    let mut right_left_tree =
-                        mem::take(&mut right_tree.as_ref().unwrap().borrow_mut().left);
-
-                    if new_right_tree.is_none() {
-                        new_right_tree = right_tree;
-                    } else {
-                        new_right_tree
-                            .as_ref()
-                            .unwrap()
-                            .borrow_mut()
-                            .insert_left_most(right_tree);
-                    }
-                    if new_left_tree.is_none() {
-                        new_left_tree = nodeptr;
-                    } else {
-                        new_left_tree
-                            .as_ref()
-                            .unwrap()
-                            .borrow_mut()
-                            .insert_right_most(nodeptr);
-                    }
-
+                    let right_tree = mem::take(&mut nodeptr.borrow_mut().right).unwrap();
+                    let right_left_tree = mem::take(&mut right_tree.borrow_mut().left).unwrap();
+                    new_right_tree.push(right_tree);
+                    new_left_tree.push(nodeptr);
                     nodeptr = right_left_tree;
                 }
-                _ => {
-                    println!("Case Error");
+            }
+        }
+    }
+
+    // bottom-up splay without parent links. the nodes on the way down to key,
+    // or to the node it would hang from, are kept on a stack and the last one
+    // is rotated up along them two steps at a time
+    fn splay_bottom_up<Q: ?Sized>(
+        root: &mut SplayNode<K, V, A>,
+        key: &Q,
+        cmp: &C,
+    ) -> Result<(), SplayError>
+    where
+        K: borrow::Borrow<Q>,
+        C: Compare<Q>,
+    {
+        let mut node = match mem::take(root) {
+            Some(node) => node,
+            None => return Err(SplayError::EmptyTree),
+        };
+        let mut path: Vec<Rc<RefCell<Node<K, V, A>>>> = Vec::new();
+        loop {
+            // lazy tags go below each node before its child is picked, the
+            // rotations then move subtrees that carry their own tags
+            node.borrow_mut().push_down();
+            let next = {
+                let current = node.borrow();
+                match cmp.compare(current.key.borrow(), key) {
+                    Ordering::Equal => None,
+                    Ordering::Greater => current.left.clone(),
+                    Ordering::Less => current.right.clone(),
+                }
+            };
+            match next {
+                Some(next) => path.push(mem::replace(&mut node, next)),
+                None => break,
+            }
+        }
+
+        // NOTE: each step leaves the node where its grandparent was, the
+        // node above the grandparent still points at the grandparent and is
+        // relinked here. its size is fixed once it is rotated itself
+        while let Some(parent) = path.pop() {
+            let grandparent = match path.pop() {
+                Some(grandparent) => grandparent,
+                None => {
+                    rotate_up(&node, &parent);
                     break;
                 }
+            };
+            if is_left_child(&node, &parent) == is_left_child(&parent, &grandparent) {
+                // zig zig, the parent goes up first
+                rotate_up(&parent, &grandparent);
+                rotate_up(&node, &parent);
+            } else {
+                // zig zag
+                rotate_up(&node, &parent);
+                relink(&grandparent, &parent, &node);
+                rotate_up(&node, &grandparent);
+            }
+            if let Some(above) = path.last() {
+                relink(above, &grandparent, &node);
             }
         }
+        *root = Some(node);
+        Ok(())
+    }
+
+    // NOTE: merge new_left_tree, new_right_tree to nodeptr X
+    //
+    // L     X    R                X
+    //  \   /\   /   =>          /  \
+    //   a b  c d              a     d
+    //                          \   /
+    //                           b c
+    fn merge(
+        node: Rc<RefCell<Node<K, V, A>>>,
+        new_left_tree: Spine<K, V, A>,
+        new_right_tree: Spine<K, V, A>,
+    ) -> Rc<RefCell<Node<K, V, A>>> {
+        let right_tree = mem::take(&mut node.borrow_mut().right);
+        let left_tree = mem::take(&mut node.borrow_mut().left);
+
+        node.borrow_mut().left = close_spine(new_left_tree, left_tree, |node| &mut node.right);
+        node.borrow_mut().right = close_spine(new_right_tree, right_tree, |node| &mut node.left);
+        node.borrow_mut().update();
+        node
     }
 }